@@ -0,0 +1,389 @@
+//! A light model of the grammar for the lints, built from the same parse as `Formatter`.
+
+use crate::{
+    formatter::{PestParser, Rule},
+    PestError, PestResult,
+};
+use pest::{iterators::Pair, Parser};
+use std::collections::HashSet;
+
+/// A span of the input, with the line and column of the start (1-based).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        let (line, col) = span.start_pos().line_col();
+        // The spans of terms include the whitespace before a missing postfix operator.
+        Span { start: span.start(), end: span.start() + span.as_str().trim_end().len(), line, col }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RuleType {
+    Normal,
+    /// `_`
+    Silent,
+    /// `@`
+    Atomic,
+    /// `$`
+    CompoundAtomic,
+    /// `!`
+    NonAtomic,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RuleDef {
+    pub name: String,
+    /// The span of the identifier
+    pub span: Span,
+    /// The start of the doc comments of the rule, or of the identifier
+    pub start: usize,
+    /// The end of the closing brace
+    pub end: usize,
+    pub ty: RuleType,
+    /// The names of the rules referenced in the expression, in the source order
+    pub references: Vec<String>,
+    pub expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ExprKind {
+    /// `"a"`, unescaped
+    Str(String),
+    /// `^"a"`, unescaped
+    Insens(String),
+    /// `'a'..'z'`
+    Range(char, char),
+    Ident(String),
+    /// `PEEK[start..end]`
+    PeekSlice(Option<i32>, Option<i32>),
+    Push(Box<Expr>),
+    /// `&e`
+    PosPred(Box<Expr>),
+    /// `!e`
+    NegPred(Box<Expr>),
+    Seq(Vec<Expr>),
+    Choice(Vec<Expr>),
+    /// `e?`
+    Opt(Box<Expr>),
+    /// `e*`
+    Rep(Box<Expr>),
+    /// `e+`
+    RepOnce(Box<Expr>),
+    /// `e{n}`
+    RepExact(Box<Expr>, u32),
+    /// `e{n,}`
+    RepMin(Box<Expr>, u32),
+    /// `e{,n}`
+    RepMax(Box<Expr>, u32),
+    /// `e{n, m}`
+    RepMinMax(Box<Expr>, u32, u32),
+    /// `#tag = e`
+    Tag(String, Box<Expr>),
+}
+
+impl Expr {
+    /// The direct sub-expressions.
+    pub(crate) fn children(&self) -> Vec<&Expr> {
+        match &self.kind {
+            ExprKind::Str(_) | ExprKind::Insens(_) | ExprKind::Range(..) | ExprKind::Ident(_) | ExprKind::PeekSlice(..) => vec![],
+            ExprKind::Seq(exprs) | ExprKind::Choice(exprs) => exprs.iter().collect(),
+            ExprKind::Push(expr) | ExprKind::PosPred(expr) | ExprKind::NegPred(expr) | ExprKind::Opt(expr) | ExprKind::Rep(expr) | ExprKind::RepOnce(expr) | ExprKind::RepExact(expr, _) | ExprKind::RepMin(expr, _) | ExprKind::RepMax(expr, _) | ExprKind::RepMinMax(expr, ..) | ExprKind::Tag(_, expr) => vec![expr],
+        }
+    }
+
+    /// Visit the expression and the sub-expressions, parents first.
+    pub(crate) fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Grammar {
+    pub rules: Vec<RuleDef>,
+}
+
+impl Grammar {
+    pub(crate) fn parse(input: &str) -> PestResult<Grammar> {
+        let pairs = match PestParser::parse(Rule::grammar_rules, input) {
+            Ok(pairs) => pairs,
+            Err(e) => return Err(PestError::ParseFail(e.to_string())),
+        };
+
+        let mut rules = vec![];
+        let mut doc_start = None;
+        for pair in pairs {
+            let start = pair.as_span().start();
+            match pair.as_rule() {
+                Rule::grammar_rule => match parse_rule(pair) {
+                    Some(mut rule) => {
+                        rule.start = doc_start.take().unwrap_or(start);
+                        rules.push(rule);
+                    }
+                    // `line_doc`
+                    None => {
+                        doc_start.get_or_insert(start);
+                    }
+                },
+                _ => doc_start = None,
+            }
+        }
+
+        Ok(Grammar { rules })
+    }
+
+    /// The names of the rules reachable from the roots through the references.
+    pub(crate) fn reachable<'a>(&'a self, roots: impl IntoIterator<Item = &'a str>) -> HashSet<&'a str> {
+        let mut reachable = HashSet::new();
+        let mut stack: Vec<&str> = roots.into_iter().collect();
+        while let Some(name) = stack.pop() {
+            if reachable.insert(name) {
+                for rule in self.rules.iter().filter(|rule| rule.name == name) {
+                    stack.extend(rule.references.iter().map(String::as_str));
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// The first rule with the name.
+    pub(crate) fn get(&self, name: &str) -> Option<&RuleDef> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+}
+
+/// Returns `None` for a `line_doc`.
+fn parse_rule(pair: Pair<'_, Rule>) -> Option<RuleDef> {
+    let (start, end) = (pair.as_span().start(), pair.as_span().end());
+    let mut pairs = pair.into_inner();
+    let identifier = pairs.next()?;
+    if identifier.as_rule() != Rule::identifier {
+        return None;
+    }
+
+    let mut ty = RuleType::Normal;
+    let mut references = vec![];
+    let mut expr = None;
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::silent_modifier => ty = RuleType::Silent,
+            Rule::atomic_modifier => ty = RuleType::Atomic,
+            Rule::compound_atomic_modifier => ty = RuleType::CompoundAtomic,
+            Rule::non_atomic_modifier => ty = RuleType::NonAtomic,
+            Rule::expression => {
+                let identifiers = pair.clone().into_inner().flatten().filter(|pair| pair.as_rule() == Rule::identifier);
+                references.extend(identifiers.map(|pair| pair.as_str().to_string()));
+                expr = Some(parse_expression(pair));
+            }
+            _ => (),
+        }
+    }
+
+    Some(RuleDef { name: identifier.as_str().to_string(), span: identifier.as_span().into(), start, end, ty, references, expr: expr? })
+}
+
+fn parse_expression(pair: Pair<'_, Rule>) -> Expr {
+    let span = pair.as_span().into();
+
+    // The sequences of terms between the `|`
+    let mut choices: Vec<Vec<Expr>> = vec![vec![]];
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::term => choices.last_mut().unwrap().push(parse_term(pair)),
+            Rule::choice_operator if !choices.last().unwrap().is_empty() => choices.push(vec![]),
+            _ => (),
+        }
+    }
+
+    let mut alternatives: Vec<Expr> = choices.into_iter().map(|terms| sequence(terms, span)).collect();
+    match alternatives.len() {
+        1 => alternatives.pop().unwrap(),
+        _ => Expr { kind: ExprKind::Choice(alternatives), span },
+    }
+}
+
+/// The sequence of the terms, or the term if there is only one.
+fn sequence(mut terms: Vec<Expr>, span: Span) -> Expr {
+    match terms.len() {
+        0 => Expr { kind: ExprKind::Seq(terms), span },
+        1 => terms.pop().unwrap(),
+        _ => {
+            let (first, last) = (terms[0].span, terms[terms.len() - 1].span);
+            Expr { kind: ExprKind::Seq(terms), span: Span { end: last.end, ..first } }
+        }
+    }
+}
+
+fn parse_term(pair: Pair<'_, Rule>) -> Expr {
+    let span = pair.as_span().into();
+    let mut tag = None;
+    let mut prefixes = vec![];
+    let mut expr = None;
+
+    for pair in pair.into_inner() {
+        let wrap = |kind: fn(Box<Expr>) -> ExprKind, expr: Option<Expr>| expr.map(|expr| Expr { kind: kind(Box::new(expr)), span });
+        let number = |pair: Pair<'_, Rule>| pair.into_inner().filter(|pair| pair.as_rule() == Rule::number).map(|pair| pair.as_str().parse().unwrap_or(u32::MAX)).collect::<Vec<u32>>();
+
+        match pair.as_rule() {
+            Rule::tag_id => tag = Some(pair.as_str()[1..].to_string()),
+            Rule::positive_predicate_operator | Rule::negative_predicate_operator => prefixes.push(pair.as_rule()),
+            Rule::expression => expr = Some(parse_expression(pair)),
+            Rule::optional_operator => expr = wrap(ExprKind::Opt, expr),
+            Rule::repeat_operator => expr = wrap(ExprKind::Rep, expr),
+            Rule::repeat_once_operator => expr = wrap(ExprKind::RepOnce, expr),
+            Rule::repeat_exact => expr = expr.map(|expr| Expr { kind: ExprKind::RepExact(Box::new(expr), number(pair)[0]), span }),
+            Rule::repeat_min => expr = expr.map(|expr| Expr { kind: ExprKind::RepMin(Box::new(expr), number(pair)[0]), span }),
+            Rule::repeat_max => expr = expr.map(|expr| Expr { kind: ExprKind::RepMax(Box::new(expr), number(pair)[0]), span }),
+            Rule::repeat_min_max => {
+                let numbers = number(pair);
+                expr = expr.map(|expr| Expr { kind: ExprKind::RepMinMax(Box::new(expr), numbers[0], numbers[1]), span });
+            }
+            Rule::opening_paren | Rule::closing_paren | Rule::assignment_operator => (),
+            _ => expr = Some(parse_terminal(pair)),
+        }
+    }
+
+    let mut expr = expr.unwrap_or(Expr { kind: ExprKind::Seq(vec![]), span });
+    for prefix in prefixes.into_iter().rev() {
+        let kind = if prefix == Rule::positive_predicate_operator { ExprKind::PosPred(Box::new(expr)) } else { ExprKind::NegPred(Box::new(expr)) };
+        expr = Expr { kind, span };
+    }
+    if let Some(tag) = tag {
+        expr = Expr { kind: ExprKind::Tag(tag, Box::new(expr)), span };
+    }
+
+    expr
+}
+
+fn parse_terminal(pair: Pair<'_, Rule>) -> Expr {
+    let span = pair.as_span().into();
+    let kind = match pair.as_rule() {
+        Rule::identifier => ExprKind::Ident(pair.as_str().to_string()),
+        Rule::string => ExprKind::Str(unescape(inner_str(pair))),
+        Rule::insensitive_string => ExprKind::Insens(unescape(pair.into_inner().next().map_or("", inner_str))),
+        Rule::range => {
+            let mut chars = pair.into_inner().filter(|pair| pair.as_rule() == Rule::character).map(|pair| unescape(inner_str(pair)).chars().next().unwrap_or_default());
+            ExprKind::Range(chars.next().unwrap_or_default(), chars.next().unwrap_or_default())
+        }
+        Rule::_push => ExprKind::Push(Box::new(pair.into_inner().find(|pair| pair.as_rule() == Rule::expression).map(parse_expression).unwrap_or(Expr { kind: ExprKind::Seq(vec![]), span }))),
+        Rule::peek_slice => {
+            let (mut start, mut end, mut after_range) = (None, None, false);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::range_operator => after_range = true,
+                    Rule::integer if after_range => end = pair.as_str().parse().ok(),
+                    Rule::integer => start = pair.as_str().parse().ok(),
+                    _ => (),
+                }
+            }
+            ExprKind::PeekSlice(start, end)
+        }
+        _ => ExprKind::Seq(vec![]),
+    };
+
+    Expr { kind, span }
+}
+
+/// The content between the quotes of a `string` or `character`.
+fn inner_str(pair: Pair<'_, Rule>) -> &str {
+    let s = pair.as_str();
+    &s[1..s.len() - 1]
+}
+
+/// Replace the escapes of pest strings, unknown escapes are kept.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('0') => out.push('\0'),
+            Some('x') => {
+                let code: String = chars.by_ref().take(2).collect();
+                out.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+            }
+            Some('u') => {
+                let code: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                out.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+            }
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The expression without the spans, e.g.: `Seq(Str("a"), Rep(Ident(b)))`.
+    fn shape(expr: &Expr) -> String {
+        let children: Vec<String> = expr.children().into_iter().map(shape).collect();
+        match &expr.kind {
+            ExprKind::Str(s) => format!("Str({:?})", s),
+            ExprKind::Insens(s) => format!("Insens({:?})", s),
+            ExprKind::Range(start, end) => format!("Range({:?}, {:?})", start, end),
+            ExprKind::Ident(name) => format!("Ident({})", name),
+            ExprKind::PeekSlice(start, end) => format!("PeekSlice({:?}, {:?})", start, end),
+            ExprKind::RepExact(_, n) | ExprKind::RepMin(_, n) | ExprKind::RepMax(_, n) => format!("{}({}, {})", kind_name(expr), children.join(", "), n),
+            ExprKind::RepMinMax(_, min, max) => format!("RepMinMax({}, {}, {})", children.join(", "), min, max),
+            ExprKind::Tag(tag, _) => format!("Tag({}, {})", tag, children.join(", ")),
+            _ => format!("{}({})", kind_name(expr), children.join(", ")),
+        }
+    }
+
+    fn kind_name(expr: &Expr) -> String {
+        format!("{:?}", expr.kind).split('(').next().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        let grammar = Grammar::parse(
+            r#"
+            /// Doc
+            a = _{ | "a\"\u{41}" ~ b* | !^"c"+ ~ 'a'..'z' | #t = (d | e){2} }
+            b = { PUSH(a) ~ PEEK[1..] ~ PEEK[..-1] ~ &d? ~ e{1,} ~ e{,2} ~ e{1, 3} }
+            "#,
+        )
+        .unwrap();
+
+        let a = &grammar.rules[0];
+        assert_eq!(("a", RuleType::Silent, 13, 3, 13), (a.name.as_str(), a.ty, a.start, a.span.line, a.span.col));
+        assert_eq!(vec!["b", "d", "e"], a.references);
+        assert_eq!(r#"Choice(Seq(Str("a\"A"), Rep(Ident(b))), Seq(NegPred(RepOnce(Insens("c"))), Range('a', 'z')), Tag(t, RepExact(Choice(Ident(d), Ident(e)), 2)))"#, shape(&a.expr));
+
+        let b = &grammar.rules[1];
+        assert_eq!(r#"Seq(Push(Ident(a)), PeekSlice(Some(1), None), PeekSlice(None, Some(-1)), PosPred(Opt(Ident(d))), RepMin(Ident(e), 1), RepMax(Ident(e), 2), RepMinMax(Ident(e), 1, 3))"#, shape(&b.expr));
+        assert_eq!(Some("b"), grammar.get("b").map(|rule| rule.name.as_str()));
+        assert_eq!(vec!["a", "b", "d", "e"], {
+            let mut names: Vec<&str> = grammar.reachable(["b"]).into_iter().collect();
+            names.sort();
+            names
+        });
+    }
+}
@@ -0,0 +1,162 @@
+use pest_fmt::Config;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// The directory of the cache in the `target` directory of the project.
+const CACHE_DIR: &str = "pestfmt-cache";
+
+/// The age after which the entries are removed, so the entries of the old sources do not pile up.
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The default directory of the cache: `target/pestfmt-cache` in the project root, which is the closest
+/// ancestor of the current directory with a `Cargo.toml`, or else with a `.git`, or else the current directory.
+pub(crate) fn default_dir() -> io::Result<PathBuf> {
+    let current = std::env::current_dir()?;
+    let root = project_root(&current).unwrap_or(current);
+    Ok(root.join("target").join(CACHE_DIR))
+}
+
+fn project_root(dir: &Path) -> Option<PathBuf> {
+    let find = |name: &str| dir.ancestors().find(|dir| dir.join(name).exists()).map(Path::to_path_buf);
+    find("Cargo.toml").or_else(|| find(".git"))
+}
+
+/// On-disk cache of the sources known to be formatted.
+///
+/// Each entry is an empty file named by the hash of the formatted source, the version of pestfmt and
+/// the resolved config, so changing any of them invalidates the entry. The entries are in a directory per
+/// version: the directories of the other versions and the entries older than `MAX_AGE` are removed on open.
+pub(crate) struct Cache {
+    dir: PathBuf,
+    salt: String,
+}
+
+impl Cache {
+    /// Open the cache in `dir`, create it if not exists, then remove the stale entries.
+    pub(crate) fn new<P: AsRef<Path>>(dir: P, config: &Config) -> io::Result<Cache> {
+        let root = dir.as_ref();
+        let dir = root.join(format!("v{}", env!("CARGO_PKG_VERSION")));
+        fs::create_dir_all(&dir)?;
+
+        let gitignore = root.join(".gitignore");
+        if !gitignore.exists() {
+            fs::write(gitignore, "*\n")?;
+        }
+        prune(root, &dir, MAX_AGE);
+
+        Ok(Cache { dir, salt: format!("{}\0{:?}\0", env!("CARGO_PKG_VERSION"), config) })
+    }
+
+    /// Whether the source is known to be formatted.
+    pub(crate) fn contains(&self, source: &str) -> bool {
+        self.path(source).exists()
+    }
+
+    /// Record the source as formatted, failures are ignored since the cache is only an optimization.
+    pub(crate) fn insert(&self, source: &str) {
+        let _ = fs::write(self.path(source), "");
+    }
+
+    fn path(&self, source: &str) -> PathBuf {
+        let hash = fnv1a(fnv1a(FNV_OFFSET, self.salt.as_bytes()), source.as_bytes());
+        self.dir.join(format!("{:016x}", hash))
+    }
+}
+
+/// Remove the directories of the other versions in `root`, and the entries of `dir` older than `max_age`.
+/// Only the names of the cache are removed, since `--cache-dir` may be any directory. Failures are ignored.
+fn prune(root: &Path, dir: &Path, max_age: Duration) {
+    let is_version = |name: &str| name.strip_prefix('v').is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()) && version.contains('.'));
+    for entry in fs::read_dir(root).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path != dir && path.is_dir() && is_version(&entry.file_name().to_string_lossy()) {
+            let _ = fs::remove_dir_all(path);
+        }
+    }
+
+    let is_entry = |name: &str| name.len() == 16 && name.chars().all(|c| c.is_ascii_hexdigit());
+    let now = SystemTime::now();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let modified = entry.metadata().and_then(|metadata| metadata.modified());
+        let stale = modified.is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() >= max_age);
+        if stale && is_entry(&entry.file_name().to_string_lossy()) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a, which is stable between builds unlike `DefaultHasher`.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(FNV_OFFSET, b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(FNV_OFFSET, b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, fnv1a(FNV_OFFSET, b"foobar"));
+    }
+
+    #[test]
+    fn test_default_dir() {
+        assert_eq!(Path::new(env!("CARGO_MANIFEST_DIR")).join("target/pestfmt-cache"), default_dir().unwrap());
+
+        let dir = std::env::temp_dir().join(format!("pestfmt-test-project-root-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("a/b/c")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        assert_eq!(Some(dir.clone()), project_root(&dir.join("a/b/c")));
+        fs::write(dir.join("a/Cargo.toml"), "").unwrap();
+        assert_eq!(Some(dir.join("a")), project_root(&dir.join("a/b/c")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("pestfmt-test-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let cache = Cache::new(&dir, &Config::default()).unwrap();
+        assert_eq!("*\n", fs::read_to_string(dir.join(".gitignore")).unwrap());
+        assert!(!cache.contains("a = { \"a\" }\n"));
+        cache.insert("a = { \"a\" }\n");
+        assert!(cache.contains("a = { \"a\" }\n"));
+        assert!(!cache.contains("a = { \"b\" }\n"));
+
+        // The entries are invalid with another config.
+        let config = Config { max_width: 80, ..Default::default() };
+        let other = Cache::new(&dir, &config).unwrap();
+        assert!(!other.contains("a = { \"a\" }\n"));
+
+        // The other versions are removed, the other files are kept.
+        fs::create_dir_all(dir.join("v0.1.0")).unwrap();
+        fs::create_dir_all(dir.join("other")).unwrap();
+        Cache::new(&dir, &Config::default()).unwrap();
+        assert!(!dir.join("v0.1.0").exists());
+        assert!(dir.join("other").exists());
+        assert!(cache.contains("a = { \"a\" }\n"));
+
+        // The old entries are removed.
+        fs::write(cache.dir.join("notes.txt"), "").unwrap();
+        prune(&dir, &cache.dir, Duration::ZERO);
+        assert!(!cache.contains("a = { \"a\" }\n"));
+        assert!(cache.dir.join("notes.txt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
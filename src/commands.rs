@@ -0,0 +1,354 @@
+//! The subcommands of the CLI.
+
+use crate::{format, write_file};
+use pest_fmt::{
+    lint::rust_rule_references as rule_references,
+    rename::{check_rename, defined_rules, inline_grammars},
+    graph::Graph,
+    json, rename_rule, rename_rust,
+    stats::Stats,
+    Config, Level, Lint, LintConfig, Linter, PestError, PestResult, RulePaths,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The options of `lint --fix`.
+pub(crate) struct Fix<'a> {
+    /// The config to format the fixed files with
+    pub format: &'a Config,
+    /// The paths of the Rust files to rename the `Rule::name` paths in, when the rules are renamed for the
+    /// naming convention
+    pub rust: &'a [PathBuf],
+    /// Rename the rules for the naming convention even without Rust files
+    pub rename_rules: bool,
+}
+
+/// Print the diagnostics of the files, returns false if there are errors.
+/// The rules of the Rust files are entry rules of their grammars.
+/// With `fix`, the problems are fixed and the fixed files are formatted with the config first.
+pub(crate) fn lint(files: &[PathBuf], config: &LintConfig, rust_rules: &RustEntryRules, fix: Option<&Fix>) -> bool {
+    let (mut errors, mut warnings) = (0, 0);
+
+    for path in files {
+        let mut config = config.clone();
+        config.entry_rules.extend(rust_rules.of(path));
+        let result = fs::read_to_string(path).map_err(Into::into).and_then(|source| match fix {
+            Some(fix) => lint_fix(path, &source, &config, fix),
+            None => Linter::with_config(&source, config).lint(),
+        });
+        let diagnostics = match result {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                errors += 1;
+                continue;
+            }
+        };
+
+        for diagnostic in diagnostics {
+            match diagnostic.level {
+                Level::Deny => errors += 1,
+                _ => warnings += 1,
+            }
+            println!("{}:{}:{}: {}[{}]: {}", path.display(), diagnostic.line, diagnostic.col, diagnostic.level, diagnostic.lint, diagnostic.message);
+            for note in diagnostic.notes {
+                println!("  {}:{}:{}: note: {}", path.display(), note.line, note.col, note.message);
+            }
+        }
+    }
+
+    println!("Checked {} files, {} errors, {} warnings", files.len(), errors, warnings);
+    errors == 0
+}
+
+/// The formats of the `graph` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum GraphFormat {
+    /// Graphviz DOT
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+    /// A JSON object with the nodes and the edges
+    Json,
+}
+
+/// Print the reference graph of the grammar, returns false on errors.
+pub(crate) fn graph(path: &Path, format: GraphFormat, rule: Option<&str>) -> bool {
+    let graph = match fs::read_to_string(path).map_err(Into::into).and_then(|source| Graph::new(&source, rule)) {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return false;
+        }
+    };
+
+    match format {
+        GraphFormat::Dot => print!("{}", graph.to_dot()),
+        GraphFormat::Mermaid => print!("{}", graph.to_mermaid()),
+        GraphFormat::Json => println!("{}", graph.to_json()),
+    }
+    true
+}
+
+/// The formats of the `stats` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum StatsFormat {
+    /// Text for humans
+    Text,
+    /// One JSON object per line for each file
+    Json,
+}
+
+/// Print the statistics of each file, returns false on errors.
+pub(crate) fn stats(files: &[PathBuf], format: StatsFormat) -> bool {
+    let mut success = true;
+    for path in files {
+        let stats = match fs::read_to_string(path).map_err(Into::into).and_then(|source| Stats::new(&source)) {
+            Ok(stats) => stats,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                success = false;
+                continue;
+            }
+        };
+
+        match format {
+            StatsFormat::Text => {
+                println!("{}:", path.display());
+                for line in stats.to_string().lines() {
+                    println!("  {}", line);
+                }
+            }
+            StatsFormat::Json => println!(r#"{{"type":"file","path":{},"stats":{}}}"#, json::quote(&path.to_string_lossy()), stats.to_json()),
+        }
+    }
+
+    success
+}
+
+/// Fix the file, then lint the fixed source.
+fn lint_fix(path: &Path, source: &str, config: &LintConfig, fix: &Fix) -> PestResult<Vec<pest_fmt::Diagnostic>> {
+    // Renaming the rules breaks the `Rule::old` paths of the Rust code, so it is opt-in without the Rust files.
+    let mut fix_config = config.clone();
+    if fix.rust.is_empty() && !fix.rename_rules {
+        fix_config.levels.insert(Lint::NamingConvention, Level::Allow);
+    }
+    let (fixed, renames) = Linter::with_config(source, fix_config).fix_renames()?;
+    if fixed == source {
+        return Linter::with_config(source, config.clone()).lint();
+    }
+
+    let fixed = format(&fixed, fix.format)?;
+    let mut rust = vec![];
+    for file in rust_files(fix.rust)? {
+        let source = fs::read_to_string(&file)?;
+        rust.push((file, source));
+    }
+    let mut renamed_files = vec![];
+    let grammar_files = [path.canonicalize()?];
+    for (old, new) in &renames {
+        for (file, output) in rename_in_rust(&rust, &grammar_files, old, new, true)? {
+            if let Some((_, source)) = rust.iter_mut().find(|(path, _)| *path == file) {
+                *source = output;
+            }
+            if !renamed_files.contains(&file) {
+                renamed_files.push(file);
+            }
+        }
+    }
+
+    write_file(path, &fixed, false)?;
+    println!("Fixed {}", path.display());
+    for (old, new) in &renames {
+        match fix.rust.is_empty() {
+            true => println!("  note: renamed rule {} to {}, the Rule::{} paths of the Rust code are not renamed", old, new, old),
+            false => println!("  note: renamed rule {} to {}", old, new),
+        }
+    }
+    for (file, source) in rust.iter().filter(|(file, _)| renamed_files.contains(file)) {
+        write_file(file, source, false)?;
+        println!("Renamed in {}", file.display());
+    }
+
+    // The entry rules read from the Rust files are renamed too.
+    let mut config = config.clone();
+    for rule in &mut config.entry_rules {
+        if let Some((_, new)) = renames.iter().find(|(old, _)| old == rule) {
+            *rule = new.clone();
+        }
+    }
+    Linter::with_config(&fixed, config).lint()
+}
+
+/// Rename the rule `old` to `new` in the `.pest` files and in the inline grammars of the `.rs` files in the
+/// paths, returns false if the rule can not be renamed. Nothing is written unless every file can be renamed.
+pub(crate) fn rename(files: &[PathBuf], paths: &[String], old: &str, new: &str, rust_paths: bool) -> io::Result<bool> {
+    if let Err(e) = check_rename(old, new) {
+        eprintln!("{}", e);
+        return Ok(false);
+    }
+
+    let mut sources = vec![];
+    for path in files.iter().filter(|path| path.extension().map_or(true, |ext| ext != "rs")) {
+        sources.push((path.clone(), false, fs::read_to_string(path)?));
+    }
+    for path in rust_files(&paths.iter().map(PathBuf::from).collect::<Vec<_>>())? {
+        sources.push((path.clone(), true, fs::read_to_string(path)?));
+    }
+
+    // The grammars may be split in several files, so the collisions are checked in all of them.
+    let mut defined = vec![];
+    for (path, rust, source) in &sources {
+        let grammars = if *rust { inline_grammars(source) } else { vec![source.clone()] };
+        for grammar in grammars {
+            match defined_rules(&grammar) {
+                Ok(names) => defined.extend(names),
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    return Ok(false);
+                }
+            }
+        }
+    }
+    if defined.iter().any(|name| name == new) {
+        eprintln!("rule {} is already defined", new);
+        return Ok(false);
+    }
+    if !defined.iter().any(|name| name == old) {
+        eprintln!("rule {} is not defined", old);
+        return Ok(false);
+    }
+
+    // The grammar files first, for the `#[grammar = "..."]` attributes of the Rust files.
+    let mut renamed = vec![];
+    for (path, _, source) in sources.iter().filter(|(_, rust, _)| !*rust) {
+        match rename_rule(source, old, new) {
+            Ok(output) if output != *source => renamed.push((path.clone(), output)),
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                return Ok(false);
+            }
+        }
+    }
+    let grammar_files: Vec<PathBuf> = renamed.iter().filter_map(|(path, _)| path.canonicalize().ok()).collect();
+
+    let rust_sources: Vec<(PathBuf, String)> = sources.iter().filter(|(_, rust, _)| *rust).map(|(path, _, source)| (path.clone(), source.clone())).collect();
+    match rename_in_rust(&rust_sources, &grammar_files, old, new, rust_paths) {
+        Ok(outputs) => renamed.extend(outputs),
+        Err(e) => {
+            eprintln!("{}", e);
+            return Ok(false);
+        }
+    }
+
+    for (path, output) in &renamed {
+        write_file(path, output, false)?;
+        println!("Renamed in {}", path.display());
+    }
+    println!("Renamed rule {} to {} in {} files", old, new, renamed.len());
+    Ok(true)
+}
+
+/// Rename the rule `old` to `new` in the inline grammars of the Rust sources, and in the `Rule::old` paths of
+/// the renamed grammar files with `rust_paths`, returns the changed sources.
+///
+/// The paths not resolved to a parser are only renamed if no other parser is found, else they are reported.
+fn rename_in_rust(sources: &[(PathBuf, String)], grammar_files: &[PathBuf], old: &str, new: &str, rust_paths: bool) -> PestResult<Vec<(PathBuf, String)>> {
+    let error = |path: &Path, e: PestError| PestError::RenameFail(format!("{}: {}", path.display(), e));
+
+    let mut results = vec![];
+    for (path, source) in sources {
+        // pest_derive looks for the grammar files in the `src` directory of the crate, then in the crate.
+        let root = manifest_dir(path);
+        let renamed_file = |file: &str| [root.join("src").join(file), root.join(file)].iter().any(|path| path.canonicalize().is_ok_and(|path| grammar_files.contains(&path)));
+        let paths = RulePaths { renamed_file: &renamed_file, unresolved: false };
+        let result = rename_rust(source, old, new, Some(&paths).filter(|_| rust_paths)).map_err(|e| error(path, e))?;
+        results.push((path, source, result));
+    }
+
+    // Without other parsers, the unresolved paths can only be of the renamed grammars.
+    let other_parsers = results.iter().any(|(_, _, result)| result.other_parsers);
+    let mut renamed = vec![];
+    for (path, source, result) in results {
+        let mut output = result.output;
+        if !result.unresolved.is_empty() && !other_parsers {
+            let paths = RulePaths { renamed_file: &|_| true, unresolved: true };
+            output = rename_rust(source, old, new, Some(&paths)).map_err(|e| error(path, e))?.output;
+        } else {
+            for line in result.unresolved {
+                eprintln!("warning: {}:{}: Rule::{} is not renamed, its Rule is not resolved to a parser", path.display(), line, old);
+            }
+        }
+        if output != *source {
+            renamed.push((path.clone(), output));
+        }
+    }
+
+    Ok(renamed)
+}
+
+/// The directory of the nearest `Cargo.toml` above the file, else the directory of the file.
+fn manifest_dir(path: &Path) -> PathBuf {
+    let dir = path.canonicalize().ok().and_then(|path| path.parent().map(Path::to_path_buf)).unwrap_or_default();
+    dir.ancestors().find(|dir| dir.join("Cargo.toml").is_file()).unwrap_or(&dir).to_path_buf()
+}
+
+/// The rules in the `Rule::name` paths of the Rust files, used as entry rules.
+#[derive(Debug, Default)]
+pub(crate) struct RustEntryRules {
+    /// The rules of the paths resolved to a parser, with the canonical paths of its grammar files
+    resolved: Vec<(PathBuf, String)>,
+    /// The rules of the paths not resolved to a parser, they are entry rules of every grammar
+    unresolved: Vec<String>,
+}
+
+impl RustEntryRules {
+    /// The entry rules of the grammar file.
+    pub(crate) fn of(&self, path: &Path) -> Vec<String> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.resolved.iter().filter(|(file, _)| *file == path).map(|(_, name)| name).chain(&self.unresolved).cloned().collect()
+    }
+}
+
+/// The rules in the `Rule::name` paths of the `.rs` files in the paths.
+pub(crate) fn rust_rule_references(paths: &[PathBuf]) -> io::Result<RustEntryRules> {
+    let mut rules = RustEntryRules::default();
+    for path in rust_files(paths)? {
+        // pest_derive looks for the grammar files in the `src` directory of the crate, then in the crate.
+        let root = manifest_dir(&path);
+        for reference in rule_references(&fs::read_to_string(&path)?) {
+            match &reference.grammars {
+                Some(grammars) => {
+                    let files = grammars.iter().flat_map(|file| [root.join("src").join(file), root.join(file)]).filter_map(|file| file.canonicalize().ok());
+                    rules.resolved.extend(files.map(|file| (file, reference.name.clone())));
+                }
+                None => rules.unresolved.push(reference.name.clone()),
+            }
+        }
+    }
+
+    rules.resolved.sort();
+    rules.resolved.dedup();
+    rules.unresolved.sort();
+    rules.unresolved.dedup();
+    Ok(rules)
+}
+
+/// The `.rs` files in the paths, sorted.
+fn rust_files(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        for entry in ignore::WalkBuilder::new(path).build() {
+            let entry = entry.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            if entry.path().is_file() && entry.path().extension().is_some_and(|ext| ext == "rs") {
+                files.push(entry.into_path());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
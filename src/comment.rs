@@ -1,36 +1,175 @@
 use crate::formatter::Rule;
 use pest::iterators::Pair;
-use text_utils::indent;
+use text_utils::indent_with;
 
-use crate::Formatter;
+use crate::{BlockCommentStyle, CommentStyle, Formatter};
 
 impl Formatter<'_> {
-    pub(super) fn format_comment(&self, pairs: Pair<Rule>) -> String {
-        let mut code = String::new();
+    /// Format the comment, the `line_end` means nothing follows it on the line,
+    /// so it can be converted to line comments by the `comment_style` option.
+    ///
+    /// The comment is wrapped to fit at the `indent` column, the caller indents the lines after the first.
+    pub(super) fn format_comment(&self, pairs: Pair<Rule>, line_end: bool, indent: usize) -> String {
         let raw = pairs.as_str().trim();
+        let leading = pairs.as_str().len() - pairs.as_str().trim_start().len();
+        let column = pairs.as_span().start_pos().line_col().1 - 1 + leading;
 
         if raw.starts_with("//") {
-            code.push_str("// ");
-            code.push_str(raw[2..raw.len()].trim());
+            self.format_line_comment(raw, indent)
         } else if raw.starts_with("/*") {
-            let raw = raw.trim_start_matches("/*").trim_end_matches("*/").trim();
+            self.format_block_comment(raw, column, line_end, indent)
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn format_line_comment(&self, raw: &str, indent: usize) -> String {
+        let text = raw[2..].trim();
+
+        // Keep the commented-out code `//foo` and separator lines `//-----`.
+        if !self.config.normalize_comments || (self.config.preserve_separator_comments && is_separator(text)) {
+            return raw.trim_end().to_string();
+        }
+
+        if self.config.comment_style == CommentStyle::Block && !text.contains("*/") {
+            return format!("/* {} */", text);
+        }
+
+        if self.config.wrap_comments {
+            let lines = fill(text, self.comment_width().saturating_sub(indent + 3));
+            if lines.len() > 1 {
+                return lines.iter().map(|line| format!("// {}", line)).collect::<Vec<_>>().join("\n");
+            }
+        }
+
+        format!("// {}", text)
+    }
+
+    /// Align the trailing comments of consecutive lines to a common column, the lines
+    /// that would be longer than `max_width` are not aligned.
+    ///
+    /// The lines are `(line, alignable)`, only the lines of rules are alignable.
+    pub(super) fn align_trailing_comments(&self, lines: &mut [(String, bool)]) {
+        let width = |code: &str| code.chars().map(|c| if c == '\t' { self.indent } else { 1 }).sum::<usize>();
 
-            let comment_lines = raw.split('\n');
-            let comment_lines: Vec<String> = comment_lines.map(|c| c.trim().to_string()).collect();
+        let mut start = 0;
+        while start < lines.len() {
+            // Find the block of consecutive lines with code and trailing comment.
+            let mut end = start;
+            while end < lines.len() && lines[end].1 {
+                match split_trailing_comment(&lines[end].0) {
+                    (code, Some(_)) if !code.trim().is_empty() => end += 1,
+                    _ => break,
+                }
+            }
+
+            // The lines too long to have the trailing comment at the next column are not aligned.
+            let fits = |line: &str| {
+                let (code, comment) = split_trailing_comment(line);
+                width(code.trim_end()) + 1 + comment.unwrap_or_default().chars().count() <= self.config.max_width
+            };
+            let column = lines[start..end].iter().filter(|(line, _)| fits(line)).map(|(line, _)| width(split_trailing_comment(line).0.trim_end())).max().unwrap_or(0) + 1;
+            for (line, _) in &mut lines[start..end] {
+                let (code, comment) = split_trailing_comment(line);
+                let (code, comment) = (code.trim_end(), comment.unwrap_or_default());
+                if width(code) < column && column + comment.chars().count() <= self.config.max_width {
+                    *line = format!("{}{}{}", code, " ".repeat(column - width(code)), comment);
+                }
+            }
+
+            start = end.max(start + 1);
+        }
+    }
+
+    /// Whether only whitespace follows the comment on its line in the source.
+    pub(super) fn is_line_end(&self, pair: &Pair<Rule>) -> bool {
+        let rest = self.input.get(pair.as_span().end()..).unwrap_or_default();
+        rest.split('\n').next().unwrap_or_default().trim().is_empty()
+    }
+
+    /// Returns the width of the wrapped comments.
+    fn comment_width(&self) -> usize {
+        self.config.comment_width.unwrap_or(self.config.max_width)
+    }
+
+    /// Format the `/* */` comment at the `column` of the source, the lines are re-indented but keep their
+    /// relative indentation, so the diagrams, code samples, nested lists and comments are kept.
+    fn format_block_comment(&self, raw: &str, column: usize, line_end: bool, indent: usize) -> String {
+        let open = if raw.starts_with("/**") && raw.len() > 4 { "/**" } else { "/*" };
+        let inner = &raw[open.len()..raw.len() - 2];
+
+        let mut lines: Vec<&str> = inner.lines().map(|line| line.trim_end()).collect();
+        // The text after `/*` is the first line, the text before `*/` is the last line.
+        let first = lines.first().copied().unwrap_or_default();
+        let first_indent = column + open.len() + first.len() - first.trim_start().len();
+        let first = first.trim_start();
+        if lines.len() > 1 && lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
 
-            code = if comment_lines.len() == 1 {
-                /* Foo */
-                format!("/* {} */", comment_lines.join("").trim())
+        let common = lines.iter().skip(1).filter(|line| !line.trim().is_empty()).map(|line| line.len() - line.trim_start().len()).min();
+        // The lines indented from the column of the first line keep the indentation relative to it,
+        // e.g.: the nested items of a list.
+        let base = match common {
+            Some(common) if !first.is_empty() && common >= first_indent => first_indent,
+            Some(common) => common,
+            None => 0,
+        };
+
+        let mut comment_lines = vec![];
+        if !first.is_empty() {
+            comment_lines.push(first.to_string());
+        }
+        if lines.len() > 1 {
+            let stars = lines[1..].iter().all(|line| line.trim().is_empty() || line.trim_start().starts_with('*'));
+            if stars && self.config.block_comment_style == BlockCommentStyle::Star {
+                // Remove the ` * ` of the star column, it will be added back later.
+                comment_lines.extend(lines[1..].iter().map(|line| {
+                    let line = line.trim_start().trim_start_matches('*');
+                    line.strip_prefix(' ').unwrap_or(line).to_string()
+                }));
             } else {
-                /*
-                  Foo
-                */
-                format!("/*\n{}*/", indent(comment_lines.join("\n"), self.indent))
+                comment_lines.extend(lines[1..].iter().map(|line| line.get(base..).unwrap_or_default().to_string()));
+            }
+        }
+
+        if self.config.comment_style == CommentStyle::Line && line_end {
+            if self.config.wrap_comments {
+                comment_lines = wrap_lines(comment_lines, self.comment_width().saturating_sub(indent + 3));
+            }
+
+            // Foo
+            let lines: Vec<String> = comment_lines.iter().map(|line| format!("// {}", line).trim_end().to_string()).collect();
+            return lines.join("\n");
+        }
+
+        if comment_lines.len() > 1 && self.config.wrap_comments {
+            let width = match self.config.block_comment_style {
+                BlockCommentStyle::Indent => self.comment_width().saturating_sub(indent + self.indent),
+                BlockCommentStyle::Star => self.comment_width().saturating_sub(indent + 3),
             };
-        } else {
-            unreachable!()
+            comment_lines = wrap_lines(comment_lines, width);
+        }
+
+        // The lines may be filled into one by the wrapping.
+        if comment_lines.len() <= 1 {
+            /* Foo */
+            return format!("{} {} */", open, comment_lines.join("").trim());
+        }
+
+        match self.config.block_comment_style {
+            /*
+                Foo
+            */
+            BlockCommentStyle::Indent => format!("{}\n{}*/", open, indent_with(comment_lines.join("\n"), &self.indent_str())),
+            BlockCommentStyle::Star => {
+                /*
+                 * Foo
+                 */
+                let body: Vec<String> = comment_lines.iter().map(|line| if line.is_empty() { " *".to_string() } else { format!(" * {}", line) }).collect();
+                format!("{}\n{}\n */", open, body.join("\n"))
+            }
         }
-        code
     }
 
     pub(super) fn format_line_doc(&self, pairs: Pair<Rule>, prefix: &str) -> String {
@@ -41,8 +180,114 @@ impl Formatter<'_> {
     }
 }
 
+/// Reflow the paragraphs of prose to the width, the indented lines, list items and blank lines are kept.
+fn wrap_lines(lines: Vec<String>, width: usize) -> Vec<String> {
+    let mut out = vec![];
+    let mut paragraph: Vec<String> = vec![];
+    for line in lines {
+        let prose = !line.is_empty() && !line.starts_with(char::is_whitespace) && !is_list_item(&line);
+        if prose {
+            paragraph.push(line);
+            continue;
+        }
+
+        out.extend(fill(&paragraph.join(" "), width));
+        paragraph.clear();
+        if is_list_item(&line) {
+            paragraph.push(line);
+        } else {
+            out.push(line);
+        }
+    }
+    out.extend(fill(&paragraph.join(" "), width));
+
+    out
+}
+
+/// Fill the words into lines no longer than the width, unless a word is longer.
+fn fill(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Whether the comment is the directive, e.g.: `// pestfmt::skip`, `//! pestfmt::skip`, `/* pestfmt-off */`.
+pub(crate) fn is_directive(raw: &str, directive: &str) -> bool {
+    let raw = raw.trim();
+    let text = match raw.strip_prefix("/*") {
+        Some(inner) => inner.trim_end_matches("*/"),
+        None => raw.trim_start_matches('/').trim_start_matches('!'),
+    };
+
+    text.trim() == directive
+}
+
+/// Whether the text of comment is a separator line, e.g.: `-----`, `=====`.
+fn is_separator(text: &str) -> bool {
+    text.chars().count() >= 3 && text.chars().all(|c| c.is_ascii_punctuation())
+}
+
+fn is_list_item(line: &str) -> bool {
+    let marker = line.split(' ').next().unwrap_or_default();
+    matches!(marker, "-" | "*" | "+") || (marker.ends_with('.') && marker[..marker.len() - 1].chars().all(|c| c.is_ascii_digit()) && marker.len() > 1)
+}
+
+/// Split a formatted line into its code and the trailing comment, if any.
+///
+/// `"a" // comment` => (`"a" `, Some(`// comment`))
+pub(crate) fn split_trailing_comment(line: &str) -> (&str, Option<&str>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => return (&line[..i], Some(&line[i..])),
+            // A `/* */` comment followed by code is inline, not trailing.
+            b'/' if bytes.get(i + 1) == Some(&b'*') => match line[i + 2..].find("*/") {
+                Some(end) if !line[i + 2 + end + 2..].trim().is_empty() => i += 2 + end + 1,
+                _ => return (&line[..i], Some(&line[i..])),
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+
+    (line, None)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::split_trailing_comment;
+
+    #[test]
+    fn test_split_trailing_comment() {
+        assert_eq!(("\"a\" ", Some("// comment")), split_trailing_comment("\"a\" // comment"));
+        assert_eq!(("\"//\" ~ '\"' ", Some("/* b */")), split_trailing_comment("\"//\" ~ '\"' /* b */"));
+        assert_eq!(("\"\\\"//\"", None), split_trailing_comment("\"\\\"//\""));
+        assert_eq!(("", Some("// comment")), split_trailing_comment("// comment"));
+        assert_eq!(("\"a\" /* b */ ~ \"c\" ", Some("// d")), split_trailing_comment("\"a\" /* b */ ~ \"c\" // d"));
+    }
 
     #[test]
     fn test_comment() {
@@ -71,6 +316,115 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_comment_normalize() {
+        expect_correction! {
+            r#"
+            //------
+            //comment
+            a = { "a" }
+            "#,
+            r#"
+            //------
+            // comment
+            a = { "a" }
+            "#,
+        };
+
+        expect_correction! {
+            crate::Config { normalize_comments: false, ..Default::default() } =>
+            r#"
+            //a = { "a" }
+            a = { "a" }
+            "#,
+            r#"
+            //a = { "a" }
+            a = { "a" }
+            "#,
+        };
+
+        expect_correction! {
+            crate::Config { preserve_separator_comments: false, ..Default::default() } =>
+            r#"
+            //------
+            a = { "a" }
+            "#,
+            r#"
+            // ------
+            a = { "a" }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_comment_style() {
+        expect_correction! {
+            crate::Config { comment_style: crate::CommentStyle::Line, ..Default::default() } =>
+            r#"
+            /* comment1 */
+            /*
+                comment2
+                  indented
+            */
+            a = { /* comment3 */ "a" }
+            b = {
+                "b" /* comment4 */
+              ~ "c"
+            }
+            c = { "c" ~ /* comment5 */ "d" | PUSH(/* comment6 */ "e") }
+            "#,
+            r#"
+            // comment1
+            // comment2
+            //   indented
+            a = { /* comment3 */ "a" }
+            b = {
+                "b" // comment4
+              ~ "c"
+            }
+            c = { "c" ~ /* comment5 */ "d" | PUSH(/* comment6 */ "e") }
+            "#,
+        };
+
+        expect_correction! {
+            crate::Config { comment_style: crate::CommentStyle::Block, ..Default::default() } =>
+            r#"
+            //comment1
+            a = { "a" }
+            "#,
+            r#"
+            /* comment1 */
+            a = { "a" }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_line_comment_wrap() {
+        expect_correction! {
+            crate::Config { wrap_comments: true, comment_width: Some(20), ..Default::default() } =>
+            r#"
+            // This is a long comment which will be wrapped
+            a = { "a" }
+            "#,
+            r#"
+            // This is a long
+            // comment which
+            // will be wrapped
+            a = { "a" }
+            "#,
+        };
+
+        // In the rules, the lines are indented and wrapped at the width left by the indent, formatting again
+        // keeps them.
+        let cases = [(crate::Config { wrap_comments: true, comment_width: Some(24), ..Default::default() }, "a = {\n// This is a long comment which will be wrapped\n\"a\"\n}\n", "a = {\n    // This is a long\n    // comment which\n    // will be wrapped\n    \"a\"\n}\n"), (crate::Config { wrap_comments: true, comment_width: Some(24), choice_style: crate::ChoiceStyle::Hanging, ..Default::default() }, "a = { // This is a long comment\n\"a\" }\n", "a = { // This is a long\n      // comment\n      \"a\"\n}\n"), (crate::Config { comment_style: crate::CommentStyle::Line, ..Default::default() }, "a = {\n/*\n  comment1\n  comment2\n*/\n\"a\"\n}\n", "a = {\n    // comment1\n    // comment2\n    \"a\"\n}\n")];
+        for (config, source, expected) in cases {
+            let formatted = crate::Formatter::with_config(source, config.clone()).format().unwrap();
+            assert_eq!(expected, formatted);
+            assert_eq!(expected, crate::Formatter::with_config(&formatted, config).format().unwrap());
+        }
+    }
+
     #[test]
     fn test_comment_keep_newline() {
         expect_correction! {
@@ -130,6 +484,144 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_block_comment_in_expr() {
+        expect_correction! {
+            r#"
+            a = { "a" ~ /* c1 */ "b" }
+            b = { ("a" /* c2 */ |  "b") ~ "c"* /* c3 */ ~ "d" }
+            "#,
+            r#"
+            a = { "a" ~ /* c1 */ "b" }
+            b = { ("a" /* c2 */ | "b") ~ "c"* /* c3 */ ~ "d" }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_block_comment_indentation() {
+        expect_correction! {
+            r#"
+            /*
+                    Diagram:
+                      a -> b
+                      |
+                      c
+
+                    /* nested
+                       comment */
+            */
+            a = { "a" }
+            /* - item 1
+                 - item 1.1
+               - item 2 */
+            b = { "b" }
+                /* - item 1
+                     - item 1.1 */
+            c = { "c" }
+            "#,
+            r#"
+            /*
+                Diagram:
+                  a -> b
+                  |
+                  c
+
+                /* nested
+                   comment */
+            */
+            a = { "a" }
+            /*
+                - item 1
+                  - item 1.1
+                - item 2
+            */
+            b = { "b" }
+            /*
+                - item 1
+                  - item 1.1
+            */
+            c = { "c" }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_block_comment_star() {
+        expect_correction! {
+            crate::Config { block_comment_style: crate::BlockCommentStyle::Star, ..Default::default() } =>
+            r#"
+            /*comment1
+              comment2*/
+            a = { "a" }
+            /**
+             * Doc
+             *   indented
+             */
+            b = { "b" }
+            "#,
+            r#"
+            /*
+             * comment1
+             * comment2
+             */
+            a = { "a" }
+            /**
+             * Doc
+             *   indented
+             */
+            b = { "b" }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_block_comment_wrap() {
+        expect_correction! {
+            crate::Config { max_width: 30, wrap_comments: true, ..Default::default() } =>
+            r#"
+            /*
+            This is a long paragraph which will be wrapped
+            and joined.
+
+            - a list item is kept
+                indented  code   is kept
+            */
+            a = { "a" }
+            "#,
+            r#"
+            /*
+                This is a long paragraph
+                which will be wrapped and
+                joined.
+
+                - a list item is kept
+                    indented  code   is kept
+            */
+            a = { "a" }
+            "#,
+        };
+
+        // Filled into one line, which is kept by formatting again.
+        let config = crate::Config { wrap_comments: true, ..Default::default() };
+        let formatted = crate::Formatter::with_config("a = {\n    \"a\"\n    /* short\n       comment */\n}\n", config.clone()).format().unwrap();
+        assert_eq!("a = {\n    \"a\" /* short comment */\n}\n", formatted);
+        assert_eq!(formatted, crate::Formatter::with_config(&formatted, config).format().unwrap());
+    }
+
+    #[test]
+    fn test_block_comment_hard_tabs() {
+        expect_correction! {
+            crate::Config { hard_tabs: true, ..Default::default() } =>
+            r#"
+            /*comment1
+            comment2*/
+            b = { "b" }
+            "#,
+            "/*\n\tcomment1\n\tcomment2\n*/\nb = { \"b\" }",
+        };
+    }
+
     #[test]
     fn test_block_comment() {
         expect_correction! {
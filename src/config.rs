@@ -0,0 +1,427 @@
+use std::str::FromStr;
+
+use toml::Value;
+
+use crate::{PestError, PestResult};
+
+/// Formatting options.
+///
+/// The CLI reads them from the `[pest]` table of `rustfmt.toml`, next to `exclude`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Maximum width of each line.
+    pub max_width: usize,
+    /// Line endings of the output.
+    pub newline_style: NewlineStyle,
+    /// Indent with tabs, the alignment still uses spaces.
+    pub hard_tabs: bool,
+    /// Layout of the multi-line block comments.
+    pub block_comment_style: BlockCommentStyle,
+    /// Reflow the prose in comments to `comment_width`.
+    pub wrap_comments: bool,
+    /// Maximum width of the wrapped comments, defaults to `max_width`.
+    pub comment_width: Option<usize>,
+    /// Add a space after `//` and trim the line comments.
+    pub normalize_comments: bool,
+    /// Keep the separator line comments such as `//-----` as is, when normalizing comments.
+    pub preserve_separator_comments: bool,
+    /// Convert the comments to line `//` or block `/* */` comments.
+    pub comment_style: CommentStyle,
+    /// Align the trailing comments of consecutive lines to a common column, the trailing comments of rules are
+    /// kept on the line of the rule instead of their own line.
+    pub align_trailing_comments: bool,
+    /// Layout of the `|` operator in multi-line expressions.
+    pub choice_style: ChoiceStyle,
+    /// Layout of the `~` operator in multi-line expressions.
+    pub sequence_style: SequenceStyle,
+    /// Break the rules longer than `max_width` into lines, at `|` first, then at `~`.
+    pub break_sequences: bool,
+    /// Maximum number of blank lines which can be put between items.
+    pub blank_lines_upper_bound: usize,
+    /// Minimum number of blank lines which must be put after a rule.
+    pub blank_lines_lower_bound: usize,
+    /// Blank line between rules.
+    pub blank_lines_between_rules: RuleSpacing,
+    /// Blank line between the grammar doc `//!` and the first rule.
+    pub blank_line_after_grammar_doc: DocSpacing,
+    /// Spaces around the `=` of the tags, `#tag = expr` or `#tag=expr`.
+    pub tag_spacing: TagSpacing,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { max_width: 100, newline_style: NewlineStyle::Auto, hard_tabs: false, block_comment_style: BlockCommentStyle::Indent, wrap_comments: false, comment_width: None, normalize_comments: true, preserve_separator_comments: true, comment_style: CommentStyle::Preserve, align_trailing_comments: false, choice_style: ChoiceStyle::Leading, sequence_style: SequenceStyle::Leading, break_sequences: false, blank_lines_upper_bound: 1, blank_lines_lower_bound: 0, blank_lines_between_rules: RuleSpacing::Preserve, blank_line_after_grammar_doc: DocSpacing::Preserve, tag_spacing: TagSpacing::Spaced }
+    }
+}
+
+impl Config {
+    /// Read options from the `[pest]` table, unknown keys are ignored.
+    pub fn from_toml(table: &Value) -> PestResult<Config> {
+        let mut config = Config::default();
+
+        if let Some(value) = table.get("max_width") {
+            config.max_width = parse_usize("max_width", value)?;
+        }
+        if let Some(value) = table.get("newline_style") {
+            config.newline_style = parse_value("newline_style", value)?;
+        }
+        if let Some(value) = table.get("hard_tabs") {
+            config.hard_tabs = parse_bool("hard_tabs", value)?;
+        }
+        if let Some(value) = table.get("block_comment_style") {
+            config.block_comment_style = parse_value("block_comment_style", value)?;
+        }
+        if let Some(value) = table.get("wrap_comments") {
+            config.wrap_comments = parse_bool("wrap_comments", value)?;
+        }
+        if let Some(value) = table.get("comment_width") {
+            config.comment_width = Some(parse_usize("comment_width", value)?);
+        }
+        if let Some(value) = table.get("normalize_comments") {
+            config.normalize_comments = parse_bool("normalize_comments", value)?;
+        }
+        if let Some(value) = table.get("preserve_separator_comments") {
+            config.preserve_separator_comments = parse_bool("preserve_separator_comments", value)?;
+        }
+        if let Some(value) = table.get("comment_style") {
+            config.comment_style = parse_value("comment_style", value)?;
+        }
+        if let Some(value) = table.get("align_trailing_comments") {
+            config.align_trailing_comments = parse_bool("align_trailing_comments", value)?;
+        }
+        if let Some(value) = table.get("choice_style") {
+            config.choice_style = parse_value("choice_style", value)?;
+        }
+        if let Some(value) = table.get("sequence_style") {
+            config.sequence_style = parse_value("sequence_style", value)?;
+        }
+        if let Some(value) = table.get("break_sequences") {
+            config.break_sequences = parse_bool("break_sequences", value)?;
+        }
+        if let Some(value) = table.get("blank_lines_upper_bound") {
+            config.blank_lines_upper_bound = parse_usize("blank_lines_upper_bound", value)?;
+        }
+        if let Some(value) = table.get("blank_lines_lower_bound") {
+            config.blank_lines_lower_bound = parse_usize("blank_lines_lower_bound", value)?;
+        }
+        if let Some(value) = table.get("blank_lines_between_rules") {
+            config.blank_lines_between_rules = parse_value("blank_lines_between_rules", value)?;
+        }
+        if let Some(value) = table.get("blank_line_after_grammar_doc") {
+            config.blank_line_after_grammar_doc = parse_value("blank_line_after_grammar_doc", value)?;
+        }
+        if let Some(value) = table.get("tag_spacing") {
+            config.tag_spacing = parse_value("tag_spacing", value)?;
+        }
+
+        if config.blank_lines_lower_bound > config.blank_lines_upper_bound {
+            return Err(PestError::InvalidConfig("blank_lines_lower_bound is greater than blank_lines_upper_bound".to_string()));
+        }
+
+        Ok(config)
+    }
+}
+
+pub(crate) fn parse_value<T: FromStr<Err = String>>(key: &str, value: &Value) -> PestResult<T> {
+    match value.as_str() {
+        Some(s) => s.parse().map_err(|e| PestError::InvalidConfig(format!("{}: {}", key, e))),
+        None => Err(PestError::InvalidConfig(format!("{}: expected a string, found `{}`", key, value))),
+    }
+}
+
+/// A string or an array of strings.
+pub(crate) fn parse_string_list(key: &str, value: &Value) -> PestResult<Vec<String>> {
+    let strings = match value {
+        Value::String(s) => Some(vec![s.clone()]),
+        Value::Array(values) => values.iter().map(|value| value.as_str().map(String::from)).collect(),
+        _ => None,
+    };
+
+    strings.ok_or_else(|| PestError::InvalidConfig(format!("{}: expected a string or an array of strings, found `{}`", key, value)))
+}
+
+fn parse_usize(key: &str, value: &Value) -> PestResult<usize> {
+    match value.as_integer() {
+        Some(n) if n >= 0 => Ok(n as usize),
+        _ => Err(PestError::InvalidConfig(format!("{}: expected a positive integer, found `{}`", key, value))),
+    }
+}
+
+fn parse_bool(key: &str, value: &Value) -> PestResult<bool> {
+    match value.as_bool() {
+        Some(b) => Ok(b),
+        None => Err(PestError::InvalidConfig(format!("{}: expected a boolean, found `{}`", key, value))),
+    }
+}
+
+/// Line endings of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewlineStyle {
+    /// Same as the first line ending of the input.
+    Auto,
+    /// `\n`
+    Unix,
+    /// `\r\n`
+    Windows,
+    /// `\r\n` on Windows, `\n` on other platforms.
+    Native,
+}
+
+impl FromStr for NewlineStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(NewlineStyle::Auto),
+            "unix" => Ok(NewlineStyle::Unix),
+            "windows" => Ok(NewlineStyle::Windows),
+            "native" => Ok(NewlineStyle::Native),
+            _ => Err(format!("unknown value `{}`, expected one of `auto`, `unix`, `windows`, `native`", s)),
+        }
+    }
+}
+
+/// Layout of the multi-line block comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockCommentStyle {
+    /// ```pest
+    /// /*
+    ///     Foo
+    /// */
+    /// ```
+    Indent,
+    /// ```pest
+    /// /*
+    ///  * Foo
+    ///  */
+    /// ```
+    Star,
+}
+
+impl FromStr for BlockCommentStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "indent" => Ok(BlockCommentStyle::Indent),
+            "star" => Ok(BlockCommentStyle::Star),
+            _ => Err(format!("unknown value `{}`, expected one of `indent`, `star`", s)),
+        }
+    }
+}
+
+/// Style of the comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentStyle {
+    /// Keep the style of the input.
+    Preserve,
+    /// Convert the block comments to line comments `//`, unless there is code after them on the line.
+    Line,
+    /// Convert the line comments to block comments `/* */`.
+    Block,
+}
+
+impl FromStr for CommentStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(CommentStyle::Preserve),
+            "line" => Ok(CommentStyle::Line),
+            "block" => Ok(CommentStyle::Block),
+            _ => Err(format!("unknown value `{}`, expected one of `preserve`, `line`, `block`", s)),
+        }
+    }
+}
+
+/// Layout of the alternatives in a multi-line choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChoiceStyle {
+    /// ```pest
+    /// a = {
+    ///     "a"
+    ///   | "b"
+    /// }
+    /// ```
+    Leading,
+    /// ```pest
+    /// a = {
+    ///     "a" |
+    ///     "b"
+    /// }
+    /// ```
+    Trailing,
+    /// ```pest
+    /// a = { "a"
+    ///     | "b"
+    /// }
+    /// ```
+    Hanging,
+}
+
+impl FromStr for ChoiceStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "leading" => Ok(ChoiceStyle::Leading),
+            "trailing" => Ok(ChoiceStyle::Trailing),
+            "hanging" => Ok(ChoiceStyle::Hanging),
+            _ => Err(format!("unknown value `{}`, expected one of `leading`, `trailing`, `hanging`", s)),
+        }
+    }
+}
+
+/// Layout of the terms in a multi-line sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceStyle {
+    /// ```pest
+    /// a = {
+    ///     "a"
+    ///   ~ "b"
+    /// }
+    /// ```
+    Leading,
+    /// ```pest
+    /// a = {
+    ///     "a" ~
+    ///     "b"
+    /// }
+    /// ```
+    Trailing,
+}
+
+impl FromStr for SequenceStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "leading" => Ok(SequenceStyle::Leading),
+            "trailing" => Ok(SequenceStyle::Trailing),
+            _ => Err(format!("unknown value `{}`, expected one of `leading`, `trailing`", s)),
+        }
+    }
+}
+
+/// Blank line between rules, in addition to `blank_lines_lower_bound`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleSpacing {
+    /// Keep the blank lines of the input.
+    Preserve,
+    /// One blank line between every rule.
+    Always,
+    /// One blank line around multi-line rules.
+    MultiLine,
+}
+
+impl FromStr for RuleSpacing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(RuleSpacing::Preserve),
+            "always" => Ok(RuleSpacing::Always),
+            "multiline" => Ok(RuleSpacing::MultiLine),
+            _ => Err(format!("unknown value `{}`, expected one of `preserve`, `always`, `multiline`", s)),
+        }
+    }
+}
+
+/// Blank line between the grammar doc and the rest of the grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocSpacing {
+    /// Keep the blank lines of the input.
+    Preserve,
+    /// One blank line.
+    Always,
+    /// No blank line.
+    Never,
+}
+
+impl FromStr for DocSpacing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(DocSpacing::Preserve),
+            "always" => Ok(DocSpacing::Always),
+            "never" => Ok(DocSpacing::Never),
+            _ => Err(format!("unknown value `{}`, expected one of `preserve`, `always`, `never`", s)),
+        }
+    }
+}
+
+/// Spaces around the `=` of the tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagSpacing {
+    /// `#tag = expr`
+    Spaced,
+    /// `#tag=expr`
+    Compact,
+}
+
+impl FromStr for TagSpacing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spaced" => Ok(TagSpacing::Spaced),
+            "compact" => Ok(TagSpacing::Compact),
+            _ => Err(format!("unknown value `{}`, expected one of `spaced`, `compact`", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let value: Value = r#"choice_style = "trailing""#.parse().unwrap();
+        let config = Config::from_toml(&value).unwrap();
+        assert_eq!(ChoiceStyle::Trailing, config.choice_style);
+
+        let value: Value = r#"
+        max_width = 80
+        newline_style = "windows"
+        hard_tabs = true
+        block_comment_style = "star"
+        wrap_comments = true
+        comment_width = 80
+        normalize_comments = false
+        comment_style = "line"
+        align_trailing_comments = true
+        sequence_style = "trailing"
+        break_sequences = true
+        tag_spacing = "compact"
+        "#
+        .parse()
+        .unwrap();
+        let config = Config::from_toml(&value).unwrap();
+        assert_eq!(80, config.max_width);
+        assert_eq!(NewlineStyle::Windows, config.newline_style);
+        assert!(config.hard_tabs);
+        assert_eq!(BlockCommentStyle::Star, config.block_comment_style);
+        assert!(config.wrap_comments);
+        assert_eq!(Some(80), config.comment_width);
+        assert!(!config.normalize_comments);
+        assert_eq!(CommentStyle::Line, config.comment_style);
+        assert!(config.align_trailing_comments);
+        assert_eq!(SequenceStyle::Trailing, config.sequence_style);
+        assert!(config.break_sequences);
+        assert_eq!(TagSpacing::Compact, config.tag_spacing);
+
+        let value: Value = r#"exclude = "src/**/*""#.parse().unwrap();
+        assert_eq!(Config::default(), Config::from_toml(&value).unwrap());
+
+        let value: Value = r#"choice_style = "middle""#.parse().unwrap();
+        assert!(Config::from_toml(&value).is_err());
+
+        let value: Value = r#"max_width = "100""#.parse().unwrap();
+        assert!(Config::from_toml(&value).is_err());
+
+        let value: Value = r#"blank_lines_lower_bound = 2"#.parse().unwrap();
+        assert!(Config::from_toml(&value).is_err());
+    }
+}
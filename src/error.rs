@@ -1,4 +1,4 @@
-use std::io;
+use std::{fmt, io};
 
 #[derive(Debug, Clone)]
 pub enum PestError {
@@ -6,10 +6,43 @@ pub enum PestError {
     Unreachable(String),
     ParseFail(String),
     FormatFail(String),
+    InvalidConfig(String),
+    RenameFail(String),
+    UndefinedRule(String),
 }
 
 pub type PestResult<T> = Result<T, PestError>;
 
+impl fmt::Display for PestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PestError::IOError(e) => write!(f, "{}", e),
+            PestError::Unreachable(e) => write!(f, "{}", e),
+            PestError::ParseFail(e) => write!(f, "{}", e),
+            PestError::FormatFail(e) => write!(f, "{}", e),
+            PestError::InvalidConfig(e) => write!(f, "invalid config: {}", e),
+            PestError::RenameFail(e) => write!(f, "{}", e),
+            PestError::UndefinedRule(e) => write!(f, "rule {} is not defined", e),
+        }
+    }
+}
+
+impl std::error::Error for PestError {}
+
+impl PestError {
+    /// The `(line, column)` of a parse error, read from the ` --> line:col` of the message of pest.
+    pub fn line_col(&self) -> Option<(usize, usize)> {
+        match self {
+            PestError::ParseFail(message) => {
+                let location = message.split("--> ").nth(1)?.split_whitespace().next()?;
+                let (line, col) = location.split_once(':')?;
+                Some((line.parse().ok()?, col.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+}
+
 impl From<io::Error> for PestError {
     fn from(e: io::Error) -> Self {
         PestError::IOError(e.to_string())
@@ -1,12 +1,16 @@
-use crate::{error::PestError::Unreachable, Formatter, GrammarRule, Node, PestError, PestResult};
+use crate::{
+    comment::{is_directive, split_trailing_comment},
+    error::PestError::Unreachable,
+    ChoiceStyle, Formatter, GrammarRule, Node, PestError, PestResult, SequenceStyle, TagSpacing,
+};
 use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::Parser;
-use text_utils::indent;
+use text_utils::{indent, indent_with};
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
-struct PestParser;
+pub(crate) struct PestParser;
 
 impl Formatter<'_> {
     pub fn format(&self) -> PestResult<String> {
@@ -19,31 +23,78 @@ impl Formatter<'_> {
         .peekable();
 
         let mut nodes = vec![];
+        let mut skip_next_rule = false;
 
         while let Some(pair) = pairs.next() {
             let span = pair.as_span();
+            let mut end = span.end();
 
             match pair.as_rule() {
+                // `// pestfmt-off` ... `// pestfmt-on`, keep the source of the region.
+                Rule::COMMENT if is_directive(pair.as_str(), "pestfmt-off") => {
+                    while let Some(next) = pairs.peek() {
+                        if next.as_rule() == Rule::EOI {
+                            break;
+                        }
+                        end = next.as_span().end();
+                        if is_directive(pairs.next().unwrap().as_str(), "pestfmt-on") {
+                            break;
+                        }
+                    }
+                    nodes.push(Node::Verbatim(self.get_str((span.start(), end)).to_string()));
+                }
                 Rule::COMMENT => {
-                    let code = self.format_comment(pair);
-                    nodes.push(Node::Comment(code));
+                    skip_next_rule |= is_directive(pair.as_str(), "pestfmt::skip") || is_directive(pair.as_str(), "pestfmt-ignore");
+
+                    let code = self.format_comment(pair, true, 0);
+                    let line = span.start_pos().line_col().0;
+                    match nodes.last_mut() {
+                        // Keep the trailing comment of rule at the end of the rule, to be aligned.
+                        Some(Node::Rule(rule)) if self.config.align_trailing_comments && rule.lines.1 == line && !code.contains('\n') => {
+                            rule.code.push(' ');
+                            rule.code.push_str(&code);
+                        }
+                        _ => nodes.push(Node::Comment(code)),
+                    }
+                }
+                // `// pestfmt::skip` before a rule, keep the source of the rule.
+                Rule::grammar_rule if skip_next_rule && pair.clone().into_inner().next().map(|p| p.as_rule()) != Some(Rule::line_doc) => {
+                    skip_next_rule = false;
+
+                    // Keep the trailing comments on the last line of the rule.
+                    let line = span.end_pos().line_col().0;
+                    while let Some(next) = pairs.peek() {
+                        if next.as_rule() != Rule::COMMENT || next.as_span().start_pos().line_col().0 != line {
+                            break;
+                        }
+                        end = pairs.next().unwrap().as_span().end();
+                    }
+                    nodes.push(Node::Verbatim(self.get_str((span.start(), end)).to_string()));
                 }
                 Rule::grammar_rule => match self.format_grammar_rule(pair) {
                     Ok(node) => nodes.push(node),
                     Err(e) => return Err(e),
                 },
+                // `//! pestfmt::skip`, keep the source of the file.
+                Rule::grammar_doc if is_directive(pair.as_str(), "pestfmt::skip") => {
+                    return Ok(if self.bom { format!("\u{feff}{}", self.input) } else { self.input.to_string() });
+                }
                 Rule::grammar_doc => nodes.push(Node::LineDoc(self.format_line_doc(pair, "//!"))),
                 _ => nodes.push(Node::Str(pair.as_str().to_string())),
             };
 
             if let Some(next) = pairs.peek() {
-                self.consume_newline(&mut nodes, (span.end(), next.as_span().start()))
+                self.consume_newline(&mut nodes, (end, next.as_span().start()))
             }
         }
 
-        Ok(self.group_output(nodes))
+        self.separate_nodes(&mut nodes);
+
+        let output = self.group_output(nodes);
+        Ok(if self.bom { format!("\u{feff}{}", output) } else { output })
     }
 
+    /// Join the nodes into the output, with the line endings of the `newline_style` option.
     fn group_output(&self, nodes: Vec<Node>) -> String {
         // println!("------ nodes: {:?}", nodes);
 
@@ -74,6 +125,10 @@ impl Formatter<'_> {
                     }
                 }
                 _ => {
+                    // The rules after a blank line are not aligned with the rules before it.
+                    if node.is_blank_line() {
+                        last = 0;
+                    }
                     group.push(node);
                 }
             }
@@ -112,19 +167,26 @@ impl Formatter<'_> {
                         rule.modifier = rule.modifier.trim().to_owned();
                     }
 
-                    line_codes.push(rule.to_string(max));
+                    // The `\r` may be left in the multi-line strings.
+                    line_codes.extend(rule.to_string(max).split('\n').map(|line| (line.strip_suffix('\r').unwrap_or(line).to_string(), true)));
+                } else if let Node::Verbatim(code) = node {
+                    line_codes.push((code.clone(), false));
                 } else {
-                    line_codes.push(node.to_string(max));
+                    line_codes.push((self.apply_newline_style(&node.to_string(max)), false));
                 }
             }
 
-            output.push_str(&line_codes.join("\n"));
-            output.push('\n');
+            if self.config.align_trailing_comments {
+                self.align_trailing_comments(&mut line_codes);
+            }
+
+            output.push_str(&line_codes.into_iter().map(|(line, _)| line).collect::<Vec<_>>().join(self.newline()));
+            output.push_str(self.newline());
         }
 
         // Remove leading and trailing whitespace
         // And add a newline at the end of the file
-        format!("{}\n", output.trim())
+        format!("{}{}", output.trim(), self.newline())
     }
 
     fn format_grammar_rule(&self, pair: Pair<Rule>) -> PestResult<Node> {
@@ -135,6 +197,7 @@ impl Formatter<'_> {
 
         let start_line = pair.as_span().start_pos().line_col().0;
         let end_line = pair.as_span().end_pos().line_col().0;
+        let mut multiline = start_line != end_line;
 
         for pair in pair.into_inner() {
             match pair.as_rule() {
@@ -147,35 +210,52 @@ impl Formatter<'_> {
                 Rule::atomic_modifier => modifier = pair.as_str().to_string(),
                 Rule::non_atomic_modifier => modifier = pair.as_str().to_string(),
                 Rule::compound_atomic_modifier => modifier = pair.as_str().to_string(),
-                Rule::expression => match self.format_expression(pair) {
-                    Ok(parts) => {
-                        if start_line == end_line {
-                            code.push(' ');
-                            code.push_str(&parts.join(" | "));
-                            code.push(' ');
-                        } else {
-                            code.push_str("\n  ");
-
-                            let mut expr_code = parts.join("\n| ");
-                            // Remove leading whitespace: " |" to "|"
-                            expr_code = expr_code.split('\n').map(|part| part.trim()).collect::<Vec<_>>().join("\n");
-
-                            code.push_str(&indent(expr_code, 2));
+                Rule::expression => {
+                    let mut parts = self.format_expression(pair)?;
+
+                    // The prefix of the first line: `a = @{ `
+                    let prefix_width = identifier.chars().count() + modifier.trim().len() + 5;
+                    if !multiline && self.config.break_sequences {
+                        let width = prefix_width + parts.join(" | ").chars().count() + 2;
+                        multiline = width > self.config.max_width;
+                    }
+
+                    if !multiline {
+                        code.push(' ');
+                        code.push_str(&parts.join(" | "));
+                        code.push(' ');
+                    } else {
+                        if self.config.break_sequences {
+                            let column = match self.config.choice_style {
+                                ChoiceStyle::Hanging => prefix_width,
+                                _ => self.indent,
+                            };
+                            parts = parts.into_iter().map(|part| self.break_sequence(part, column)).collect();
                         }
+
+                        self.format_choices(&mut code, parts);
                     }
-                    Err(e) => return Err(e),
-                },
+                }
                 Rule::COMMENT => {
-                    let comment = self.format_comment(pair);
+                    let hanging = self.config.choice_style == ChoiceStyle::Hanging;
+                    // The lines after the first are indented as the first, the hanging lines are relative to `{`.
+                    let (column, line_indent) = match hanging {
+                        true => (identifier.chars().count() + modifier.trim().len() + 5, "  ".to_string()),
+                        false => (self.indent, self.indent_str()),
+                    };
+                    let comment = self.format_comment(pair, multiline, column);
 
-                    if start_line == end_line {
+                    if !multiline {
                         code.push(' ');
+                    } else if hanging {
+                        // Comment lines are aligned with the terms, see `GrammarRule::to_string`.
+                        code.push_str(if code == "{" { " " } else { "\n  " });
                     } else {
                         code.push('\n');
-                        code.push_str(&" ".repeat(4));
+                        code.push_str(&self.indent_str());
                     }
 
-                    code.push_str(&comment);
+                    code.push_str(&comment.replace('\n', &format!("\n{}", line_indent)));
                 }
                 Rule::line_doc => {
                     return Ok(Node::LineDoc(self.format_line_doc(pair, "///")));
@@ -184,34 +264,128 @@ impl Formatter<'_> {
             };
         }
 
+        let hanging = multiline && self.config.choice_style == ChoiceStyle::Hanging;
+        if hanging && !code.ends_with('\n') {
+            code.push('\n');
+        }
+
         code.push('}');
-        Ok(Node::Rule(GrammarRule { identifier, modifier, code, lines: (start_line, end_line) }))
+        Ok(Node::Rule(GrammarRule { identifier, modifier, code, lines: (start_line, end_line), hanging }))
+    }
+
+    /// Break an alternative longer than `max_width` at each `~` of the top level, by the `sequence_style` option.
+    ///
+    /// The `column` is the start of the alternative, the alternatives already broken by comments are kept.
+    fn break_sequence(&self, part: String, column: usize) -> String {
+        // Keep the room for the trailing ` |`
+        if part.contains('\n') || column + part.trim().chars().count() + 2 <= self.config.max_width {
+            return part;
+        }
+
+        let terms = split_sequence(part.trim());
+        match self.config.sequence_style {
+            SequenceStyle::Leading => terms.join("\n~ "),
+            SequenceStyle::Trailing => terms.join(" ~\n"),
+        }
+    }
+
+    /// Push the alternatives of a multi-line rule body into `code`, by the `choice_style` option.
+    fn format_choices(&self, code: &mut String, parts: Vec<String>) {
+        // The alternatives end with `\n` after the trailing comments, which adds a blank line before the next `|`
+        // and would split the aligned lines, see `test_trailing_comment_before_choice`.
+        let parts: Vec<String> = match self.config.align_trailing_comments {
+            true => parts.into_iter().map(|part| part.trim_end().to_string()).collect(),
+            false => parts,
+        };
+
+        match self.config.choice_style {
+            ChoiceStyle::Leading if self.config.hard_tabs => {
+                // The operators can not hang in a tab, so all the lines have the same indent.
+                let expr_code = parts.join("\n| ");
+                let expr_code = expr_code.split('\n').map(|part| part.trim()).collect::<Vec<_>>().join("\n");
+
+                code.push('\n');
+                code.push_str(&indent_with(expr_code, &self.indent_str()));
+            }
+            ChoiceStyle::Leading => {
+                code.push_str("\n  ");
+
+                let mut expr_code = parts.join("\n| ");
+                // Remove leading whitespace: " |" to "|"
+                // The operators are hanging in the indent, so the terms after a trailing `~` are indented more.
+                expr_code = expr_code.split('\n').map(|part| part.trim()).enumerate().map(|(i, part)| if i == 0 || part.is_empty() || starts_with_operator(part) { part.to_string() } else { format!("  {}", part) }).collect::<Vec<_>>().join("\n");
+
+                code.push_str(&indent(expr_code, 2));
+            }
+            ChoiceStyle::Trailing => {
+                code.push('\n');
+                code.push_str(&indent_with(join_trailing_choices(parts, false), &self.indent_str()));
+            }
+            ChoiceStyle::Hanging => {
+                let expr_code = parts.join("\n| ");
+                let lines = expr_code.split('\n').map(|part| part.trim()).filter(|part| !part.is_empty());
+
+                // The lines are relative to the `{`, the `GrammarRule::to_string` will indent them.
+                for line in lines {
+                    if code == "{" {
+                        code.push(' ');
+                    } else if line.starts_with('|') {
+                        code.push('\n');
+                    } else {
+                        code.push_str("\n  ");
+                    }
+                    code.push_str(line);
+                }
+            }
+        }
     }
 
     fn format_expression(&self, pairs: Pair<Rule>) -> PestResult<Vec<String>> {
         let mut code = vec![];
         let mut term = String::new();
-        for pair in pairs.into_inner() {
+        let mut pairs = pairs.into_inner().peekable();
+        while let Some(pair) = pairs.next() {
             match pair.as_rule() {
                 Rule::WHITESPACE => continue,
                 Rule::COMMENT => {
-                    let comment = self.format_comment(pair);
-                    if !term.is_empty() {
+                    let line_end = self.is_line_end(&pair);
+                    let comment = self.format_comment(pair, line_end, self.indent);
+                    if !term.is_empty() && !term.ends_with(' ') {
                         term.push(' ');
                     }
                     term.push_str(&comment);
-                    term.push('\n');
+                    // A `/* */` comment followed by code on the same line stays inline.
+                    term.push(if line_end { '\n' } else { ' ' });
                 }
                 Rule::choice_operator => {
-                    code.push(term.clone());
+                    // The comment at the end of the line of a trailing `|` belongs to the alternative before it.
+                    let line = pair.as_span().start_pos().line_col().0;
+                    let trailing = |next: &Pair<Rule>| next.as_rule() == Rule::COMMENT && next.as_span().start_pos().line_col().0 == line && self.is_line_end(next);
+                    if let Some(comment) = pairs.next_if(trailing) {
+                        let comment = self.format_comment(comment, true, self.indent);
+                        let last = term.trim_end().rsplit('\n').next().unwrap_or_default();
+                        let separator = if split_trailing_comment(last).1.is_some() { '\n' } else { ' ' };
+                        term = format!("{}{}{}\n", term.trim_end(), separator, comment);
+                    }
+
+                    code.push(term.strip_suffix(' ').unwrap_or(&term).to_string());
                     term.clear();
                 }
                 Rule::sequence_operator => {
                     if !term.ends_with('\n') {
-                        term.push(' ')
+                        if !term.ends_with(' ') {
+                            term.push(' ');
+                        }
+                        term.push('~');
+                        term.push(' ');
+                    } else if self.config.sequence_style == SequenceStyle::Trailing {
+                        // Move the `~` in front of the trailing comment.
+                        term = push_trailing_operator(&term, "~");
+                        term.push('\n');
+                    } else {
+                        term.push('~');
+                        term.push(' ');
                     }
-                    term.push('~');
-                    term.push(' ')
                 }
                 Rule::term => match self.format_term(pair) {
                     Ok(string) => term.push_str(&string),
@@ -220,7 +394,7 @@ impl Formatter<'_> {
                 _ => return Err(Unreachable(unreachable_rule!())),
             };
         }
-        code.push(term.clone());
+        code.push(term.strip_suffix(' ').unwrap_or(&term).to_string());
         Ok(code)
     }
 
@@ -230,21 +404,19 @@ impl Formatter<'_> {
             match pair.as_rule() {
                 Rule::WHITESPACE => continue,
                 Rule::COMMENT => {
-                    let comment = self.format_comment(pair);
-                    if !code.ends_with('\n') {
+                    let line_end = self.is_line_end(&pair);
+                    let comment = self.format_comment(pair, line_end, self.indent);
+                    if !code.is_empty() && !code.ends_with(['\n', ' ', '(']) {
                         code.push(' ')
                     }
                     code.push_str(&comment);
-                    code.push('\n');
-                }
-                Rule::tag_id => {
-                    code.push_str(pair.as_str());
-                    code.push(' ');
-                }
-                Rule::assignment_operator => {
-                    code.push('=');
-                    code.push(' ');
+                    code.push(if line_end { '\n' } else { ' ' });
                 }
+                Rule::tag_id => code.push_str(pair.as_str()),
+                Rule::assignment_operator => match self.config.tag_spacing {
+                    TagSpacing::Spaced => code.push_str(" = "),
+                    TagSpacing::Compact => code.push('='),
+                },
                 Rule::insensitive_string => {
                     code.push('^');
                     for inner in pair.into_inner() {
@@ -257,10 +429,10 @@ impl Formatter<'_> {
                 }
                 Rule::range => code.push_str(pair.as_str()),
                 Rule::expression => {
-                    let e = self.format_expression(pair);
-                    match e {
-                        Ok(expression) => code.push_str(&expression.join(" | ")),
-                        Err(e) => return Err(e),
+                    let parts = self.format_expression(pair)?;
+                    match self.config.choice_style {
+                        ChoiceStyle::Trailing => code.push_str(&join_trailing_choices(parts, true)),
+                        _ => code.push_str(&parts.join(" | ")),
                     }
                 }
                 Rule::_push => match self.format_term(pair) {
@@ -287,6 +459,88 @@ impl Formatter<'_> {
     }
 }
 
+/// Join the alternatives with a `|` at the end of each line, in front of the trailing comment if any.
+///
+/// The `inline` alternatives are only broken after comments, otherwise each alternative has its own line.
+fn join_trailing_choices(parts: Vec<String>, inline: bool) -> String {
+    let mut lines: Vec<String> = vec![];
+    let count = parts.len();
+    for (i, part) in parts.into_iter().enumerate() {
+        let part = if i + 1 < count { push_trailing_operator(&part, "|") } else { part };
+        let mut part_lines: Vec<String> = part.split('\n').map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect();
+
+        // Alternatives without line breaks are kept on the same line: `"a" | "b"`
+        match lines.last_mut() {
+            Some(prev) if inline && prev.ends_with('|') && !part_lines.is_empty() => {
+                let first = part_lines.remove(0);
+                prev.push(' ');
+                prev.push_str(&first);
+            }
+            _ => {}
+        }
+        lines.extend(part_lines);
+    }
+
+    lines.join("\n")
+}
+
+/// Put the operator after the last line that has code, in front of its trailing comment.
+/// The lines are trimmed, and own line comments stay after the operator.
+fn push_trailing_operator(code: &str, operator: &str) -> String {
+    let mut lines: Vec<String> = code.split('\n').map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect();
+
+    let last = lines.iter().rposition(|line| !split_trailing_comment(line).0.trim().is_empty());
+    if let Some(line) = last.map(|idx| &mut lines[idx]) {
+        *line = match split_trailing_comment(line) {
+            (code, Some(comment)) => format!("{} {} {}", code.trim_end(), operator, comment),
+            (code, None) => format!("{} {}", code, operator),
+        };
+    }
+
+    lines.join("\n")
+}
+
+/// Split the terms of a sequence at the `~` of the top level, `"a" ~ ("b" ~ "c")` => [`"a"`, `("b" ~ "c")`].
+/// The strings and the comments are skipped.
+fn split_sequence(code: &str) -> Vec<&str> {
+    let bytes = code.as_bytes();
+    let mut terms = vec![];
+    let mut depth = 0_usize;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = code[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 1),
+            b'/' if bytes.get(i + 1) == Some(&b'/') => i = code[i..].find('\n').map_or(bytes.len(), |end| i + end),
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b'~' if depth == 0 => {
+                terms.push(code[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    terms.push(code[start..].trim());
+
+    terms
+}
+
+/// Whether the formatted line starts with an operator or a comment, which are hanging in the indent.
+fn starts_with_operator(line: &str) -> bool {
+    line.starts_with('|') || line.starts_with('~') || line.starts_with("//") || line.starts_with("/*")
+}
+
 #[allow(dead_code)]
 fn format_repeat_exact(pairs: Pair<Rule>) -> String {
     let mut code = String::new();
@@ -318,6 +572,8 @@ fn format_repeat_min_max(pairs: Pair<Rule>) -> PestResult<String> {
 
 #[cfg(test)]
 mod tests {
+    use crate::{ChoiceStyle, Config, NewlineStyle, SequenceStyle, TagSpacing};
+
     #[test]
     fn test_basic() {
         expect_correction! {
@@ -425,6 +681,352 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_choice_style() {
+        expect_correction! {
+            Config { choice_style: ChoiceStyle::Trailing, ..Default::default() } =>
+            r#"
+            a = { "a" ~ "b"
+            | "c" // comment1
+            | ("d" // comment2
+            | "e" | "f")
+            }
+            b = { "b" }
+            "#,
+            r#"
+            a = {
+                "a" ~ "b" |
+                "c" | // comment1
+                ("d" | // comment2
+                "e" | "f")
+            }
+            b = { "b" }
+            "#,
+        };
+
+        // Formatting again keeps the comment after a trailing `|` with the alternative before it.
+        let cases = [(false, "a = {\n \"x\"\n | \"y\" // T\n | \"z\"\n}\n", "a = {\n    \"x\" |\n    \"y\" | // T\n    \"z\"\n}\n"), (true, "a = {\n \"x\" // 1\n | \"yyy\" // 2\n | (\"z\" // 3\n | \"w\")\n}\n", "a = {\n    \"x\" |   // 1\n    \"yyy\" | // 2\n    (\"z\" |  // 3\n    \"w\")\n}\n")];
+        for (align_trailing_comments, source, expected) in cases {
+            let config = Config { choice_style: ChoiceStyle::Trailing, align_trailing_comments, ..Default::default() };
+            let formatted = crate::Formatter::with_config(source, config.clone()).format().unwrap();
+            assert_eq!(expected, formatted);
+            assert_eq!(expected, crate::Formatter::with_config(&formatted, config).format().unwrap());
+        }
+
+        expect_correction! {
+            Config { choice_style: ChoiceStyle::Hanging, ..Default::default() } =>
+            r#"
+            a = { "a" ~ "b"
+            | "c" // comment1
+            | ("d" // comment2
+            | "e" | "f")
+            }
+            b = { "b" }
+            "#,
+            r#"
+            a = { "a" ~ "b"
+                | "c" // comment1
+                | ("d" // comment2
+                | "e" | "f")
+            }
+            b = { "b" }
+            "#,
+        };
+
+        expect_correction! {
+            Config { choice_style: ChoiceStyle::Hanging, ..Default::default() } =>
+            r#"
+            ab = @{ "a" }
+            c = _{ // comment
+              "c"
+              ~ "d"
+              | "e" }
+            "#,
+            r#"
+            ab = @{ "a" }
+            c  = _{ // comment
+                    "c" ~ "d"
+                  | "e"
+            }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_break_sequences() {
+        let config = Config { max_width: 40, break_sequences: true, ..Default::default() };
+        expect_correction! {
+            config.clone() =>
+            r#"
+            a = { "a" ~ "b" }
+
+            long_rule = { "test" ~ ("rule" ~ "two") ~ "with" ~ "long" | "b" }
+
+            multi = {
+                "with" ~ "a" ~ "long" ~ "name" ~ "and" ~ "more"
+              | "b"
+            }
+            "#,
+            r#"
+            a = { "a" ~ "b" }
+
+            long_rule = {
+                "test"
+              ~ ("rule" ~ "two")
+              ~ "with"
+              ~ "long"
+              | "b"
+            }
+
+            multi = {
+                "with"
+              ~ "a"
+              ~ "long"
+              ~ "name"
+              ~ "and"
+              ~ "more"
+              | "b"
+            }
+            "#,
+        };
+
+        expect_correction! {
+            Config { sequence_style: SequenceStyle::Trailing, ..config } =>
+            r#"
+            long_rule = { "test" ~ ("rule" ~ "two") ~ "with" ~ "long" | "b" }
+            "#,
+            r#"
+            long_rule = {
+                "test" ~
+                ("rule" ~ "two") ~
+                "with" ~
+                "long"
+              | "b"
+            }
+            "#,
+        };
+
+        // The `~` in comments are not broken.
+        let source = "long_rule = { \"test\" ~ /* x ~ y */ \"rule\" ~ \"with\" ~ \"long\" }\n";
+        let expected = "long_rule = {\n    \"test\"\n  ~ /* x ~ y */ \"rule\"\n  ~ \"with\"\n  ~ \"long\"\n}\n";
+        let formatted = crate::Formatter::with_config(source, config.clone()).format().unwrap();
+        assert_eq!(expected, formatted);
+        assert_eq!(expected, crate::Formatter::with_config(&formatted, config).format().unwrap());
+    }
+
+    #[test]
+    fn test_sequence_style() {
+        expect_correction! {
+            Config { sequence_style: SequenceStyle::Trailing, ..Default::default() } =>
+            r#"
+            a = { "a" // comment1
+                ~ "b" ~ "c" //comment2
+                // comment3
+            ~ "d"
+            }"#,
+            r#"
+            a = {
+                "a" ~ // comment1
+                "b" ~ "c" ~ // comment2
+              // comment3
+                "d"
+            }"#,
+        };
+    }
+
+    #[test]
+    fn test_hard_tabs() {
+        expect_correction! {
+            Config { hard_tabs: true, ..Default::default() } =>
+            r#"
+            a = { "a" }
+            multi = { // comment
+            "a" ~ "b"
+            | ("c" // comment1
+            | "d")
+            }
+            "#,
+            "a     = { \"a\" }\nmulti = {\n\t// comment\n\t\"a\" ~ \"b\"\n\t| (\"c\" // comment1\n\t| \"d\")\n}",
+        };
+
+        expect_correction! {
+            Config { hard_tabs: true, choice_style: ChoiceStyle::Trailing, ..Default::default() } =>
+            r#"
+            multi = {
+            "a" ~ "b"
+            | "c"
+            }
+            "#,
+            "multi = {\n\t\"a\" ~ \"b\" |\n\t\"c\"\n}",
+        };
+    }
+
+    #[test]
+    fn test_trailing_comment() {
+        expect_correction! {
+            r#"
+            a = { "a" } //comment1
+            bc = { "b" } /* comment2 */
+            // comment3
+            "#,
+            r#"
+            a  = { "a" }
+            // comment1
+            bc = { "b" }
+            /* comment2 */
+            // comment3
+            "#,
+        };
+
+        expect_correction! {
+            Config { align_trailing_comments: true, ..Default::default() } =>
+            r#"
+            a = { "a" } //comment1
+            bc = { "b" } /* comment2 */
+            // comment3
+            "#,
+            r#"
+            a  = { "a" } // comment1
+            bc = { "b" } /* comment2 */
+            // comment3
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_align_trailing_comments() {
+        expect_correction! {
+            Config { align_trailing_comments: true, max_width: 40, ..Default::default() } =>
+            r#"
+            a = { "a" } // comment1
+            bc = { "b" | "c" } // comment2
+            d = { "d" }
+            e = { "e" } // comment3
+            long_rule = {
+              "0" // comment4
+              | "123" // comment5
+              | "1234567890123456789012345" // comment6
+            }
+            "#,
+            r#"
+            a         = { "a" }       // comment1
+            bc        = { "b" | "c" } // comment2
+            d         = { "d" }
+            e         = { "e" } // comment3
+            long_rule = {
+                "0"   // comment4
+              | "123" // comment5
+              | "1234567890123456789012345" // comment6
+            }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_trailing_comment_before_choice() {
+        // A blank line is kept after the trailing comment of an alternative, as the input had a line break.
+        expect_correction! {
+            r#"
+            a = { "a" | "b" //comment1
+                | "c" }
+            "#,
+            r#"
+            a = {
+                "a"
+              | "b" // comment1
+
+              | "c"
+            }
+            "#,
+        };
+
+        // Unless the trailing comments are aligned.
+        expect_correction! {
+            Config { align_trailing_comments: true, ..Default::default() } =>
+            r#"
+            a = { "a" | "b" //comment1
+                | "c" }
+            "#,
+            r#"
+            a = {
+                "a"
+              | "b" // comment1
+              | "c"
+            }
+            "#,
+        };
+
+        expect_correction! {
+            Config { choice_style: ChoiceStyle::Trailing, ..Default::default() } =>
+            r#"
+            a = { "a" | "b" //comment1
+                | "c" }
+            "#,
+            r#"
+            a = {
+                "a" |
+                "b" | // comment1
+                "c"
+            }
+            "#,
+        };
+    }
+
+    #[test]
+    fn test_skip_directives() {
+        expect_correction! {
+            r#"
+            a = {"a"}
+            // pestfmt::skip
+            /// Doc
+            matrix = { "a" | "b"
+                     | "c" | "d" }
+            b = {"b"}
+            // pestfmt-off
+            c = {"c"}
+            /*   keep   */
+            // pestfmt-on
+            d = {"d"}
+            "#,
+            r#"
+            a = { "a" }
+            // pestfmt::skip
+            /// Doc
+            matrix = { "a" | "b"
+                     | "c" | "d" }
+            b = { "b" }
+            // pestfmt-off
+            c = {"c"}
+            /*   keep   */
+            // pestfmt-on
+            d = { "d" }
+            "#,
+        };
+
+        let source = "//! pestfmt::skip\r\na={\"a\"}  \r\n\r\n\r\n";
+        assert_eq!(source, crate::Formatter::new(source).format().unwrap());
+        // The trailing comments of a skipped rule are kept on its line.
+        expect_correction! {
+            r#"
+            // pestfmt::skip
+            a = {"a"} /* keep */ // keep
+            b={"b"} // comment
+            "#,
+            r#"
+            // pestfmt::skip
+            a = {"a"} /* keep */ // keep
+            b = { "b" }
+            // comment
+            "#,
+        };
+
+        // The skipped regions keep their line endings.
+        let source = "// pestfmt::skip\na = {\n\"a\" }\r\n// pestfmt-off\r\nb={\"b\"}\n// pestfmt-on\r\nc={\"c\"}\r\n";
+        let config = Config { newline_style: NewlineStyle::Windows, ..Default::default() };
+        let expected = "// pestfmt::skip\r\na = {\n\"a\" }\r\n// pestfmt-off\r\nb={\"b\"}\n// pestfmt-on\r\nc = { \"c\" }\r\n";
+        assert_eq!(expected, crate::Formatter::with_config(source, config).format().unwrap());
+    }
+
     #[test]
     fn test_tag() {
         expect_correction! {
@@ -437,5 +1039,15 @@ mod tests {
             another_rule_with_a_long_name = { "test" ~ "rule" ~ "two" ~ "with" ~ "a" ~ "long" ~ "name" }
             "#,
         }
+
+        expect_correction! {
+            Config { tag_spacing: TagSpacing::Compact, ..Default::default() } =>
+            r#"
+            a = { #key   =  key ~ "=" ~ #value= (value | #other =other) }
+            "#,
+            r#"
+            a = { #key=key ~ "=" ~ #value=(value | #other=other) }
+            "#,
+        }
     }
 }
@@ -0,0 +1,67 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The files in the git repository of `dir` that differ from the revision `rev` in the working tree or the
+/// index, including the untracked files. Returns the canonical paths.
+pub(crate) fn changed_files<P: AsRef<Path>>(dir: P, rev: &str) -> io::Result<HashSet<PathBuf>> {
+    let dir = dir.as_ref();
+    let root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"])?.trim_end_matches(['\n', '\r']));
+
+    let mut names = git(dir, &["diff", "--name-only", "-z", "--no-renames", rev, "--"])?;
+    names.push_str(&git(dir, &["ls-files", "--others", "--exclude-standard", "-z", "--full-name"])?);
+
+    Ok(names.split('\0').filter(|name| !name.is_empty()).filter_map(|name| root.join(name).canonicalize().ok()).collect())
+}
+
+/// Run git in `dir` and return its stdout.
+fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let message = format!("git {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+        return Err(io::Error::new(io::ErrorKind::Other, message));
+    }
+
+    String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_changed_files() {
+        let root = std::env::temp_dir().join(format!("pestfmt-test-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        let run = |args: &[&str]| git(&root, &[&["-c", "user.name=pestfmt", "-c", "user.email=pestfmt@example.com"], args].concat()).unwrap();
+
+        run(&["init", "-q"]);
+        fs::write(root.join("a.pest"), "a = { \"a\" }\n").unwrap();
+        fs::write(root.join("b.pest"), "b = { \"b\" }\n").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "first"]);
+        let first = run(&["rev-parse", "HEAD"]);
+
+        fs::write(root.join("sub/c.pest"), "c = { \"c\" }\n").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "second"]);
+
+        // Modified, staged and untracked files.
+        fs::write(root.join("a.pest"), "a={\"a\"}\n").unwrap();
+        fs::write(root.join("d.pest"), "d={\"d\"}\n").unwrap();
+        run(&["add", "d.pest"]);
+        fs::write(root.join("sub/e.pest"), "e={\"e\"}\n").unwrap();
+
+        let expected = |names: &[&str]| names.iter().map(|name| root.join(name).canonicalize().unwrap()).collect::<HashSet<_>>();
+        assert_eq!(expected(&["a.pest", "d.pest", "sub/e.pest"]), changed_files(&root, "HEAD").unwrap());
+        assert_eq!(expected(&["a.pest", "d.pest", "sub/c.pest", "sub/e.pest"]), changed_files(root.join("sub"), first.trim()).unwrap());
+        assert!(changed_files(&root, "no-such-rev").is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+//! The reference graph of the rules, in Graphviz DOT, Mermaid or JSON.
+
+use crate::{
+    ast::{Grammar, RuleType},
+    json, PestError, PestResult,
+};
+
+/// A rule of the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    /// One of `_`, `@`, `$` and `!`
+    pub modifier: Option<char>,
+}
+
+impl Node {
+    pub fn is_silent(&self) -> bool {
+        self.modifier == Some('_')
+    }
+
+    /// Atomic or compound-atomic.
+    pub fn is_atomic(&self) -> bool {
+        matches!(self.modifier, Some('@') | Some('$'))
+    }
+}
+
+/// The rules as nodes and the references between them as edges, in the source order.
+///
+/// The references to builtin or undefined rules are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    /// `(from, to)`, each pair once
+    pub edges: Vec<(String, String)>,
+}
+
+impl Graph {
+    /// Build the graph of the grammar, or only of the rules reachable from `root`.
+    pub fn new(input: &str, root: Option<&str>) -> PestResult<Graph> {
+        let grammar = Grammar::parse(input.trim_start_matches('\u{feff}'))?;
+        let reachable = match root {
+            Some(root) if grammar.get(root).is_none() => return Err(PestError::UndefinedRule(root.to_string())),
+            Some(root) => Some(grammar.reachable([root])),
+            None => None,
+        };
+
+        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        for rule in &grammar.rules {
+            let included = reachable.as_ref().map_or(true, |reachable| reachable.contains(rule.name.as_str()));
+            if !included || graph.nodes.iter().any(|node| node.name == rule.name) {
+                continue;
+            }
+
+            let modifier = match rule.ty {
+                RuleType::Normal => None,
+                RuleType::Silent => Some('_'),
+                RuleType::Atomic => Some('@'),
+                RuleType::CompoundAtomic => Some('$'),
+                RuleType::NonAtomic => Some('!'),
+            };
+            graph.nodes.push(Node { name: rule.name.clone(), modifier });
+
+            for reference in &rule.references {
+                let edge = (rule.name.clone(), reference.clone());
+                if grammar.get(reference).is_some() && !graph.edges.contains(&edge) {
+                    graph.edges.push(edge);
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// The graph in the Graphviz DOT language, silent rules are dashed and atomic rules are boxes.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph grammar {\n");
+        for node in &self.nodes {
+            let mut attributes = vec![];
+            if let Some(modifier) = node.modifier {
+                attributes.push(format!("modifier=\"{}\"", modifier));
+            }
+            match node.modifier {
+                Some('_') => attributes.push("style=dashed".to_string()),
+                Some('@') => attributes.push("shape=box".to_string()),
+                Some('$') => attributes.push("shape=box, style=rounded".to_string()),
+                _ => (),
+            }
+
+            if attributes.is_empty() {
+                out.push_str(&format!("    {};\n", json::quote(&node.name)));
+            } else {
+                out.push_str(&format!("    {} [{}];\n", json::quote(&node.name), attributes.join(", ")));
+            }
+        }
+        for (from, to) in &self.edges {
+            out.push_str(&format!("    {} -> {};\n", json::quote(from), json::quote(to)));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The graph as a Mermaid flowchart, the modifiers are classes.
+    pub fn to_mermaid(&self) -> String {
+        // The names are not used as ids, as `end` is a keyword of Mermaid.
+        let id = |name: &str| self.nodes.iter().position(|node| node.name == name).map(|i| format!("n{}", i)).unwrap_or_default();
+
+        let mut out = String::from("flowchart LR\n");
+        out.push_str("    classDef silent stroke-dasharray: 5 5\n");
+        out.push_str("    classDef atomic stroke-width: 3px\n");
+        out.push_str("    classDef compound_atomic stroke-width: 3px, stroke-dasharray: 5 5\n");
+        out.push_str("    classDef non_atomic stroke-width: 0.5px\n");
+        for node in &self.nodes {
+            let class = match node.modifier {
+                Some('_') => ":::silent",
+                Some('@') => ":::atomic",
+                Some('$') => ":::compound_atomic",
+                Some('!') => ":::non_atomic",
+                _ => "",
+            };
+            out.push_str(&format!("    {}[\"{}\"]{}\n", id(&node.name), node.name, class));
+        }
+        for (from, to) in &self.edges {
+            out.push_str(&format!("    {} --> {}\n", id(from), id(to)));
+        }
+        out
+    }
+
+    /// The graph as a JSON object with the `nodes` and the `edges`.
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let modifier = node.modifier.map_or("null".to_string(), |modifier| json::quote(&modifier.to_string()));
+                format!(r#"{{"name":{},"modifier":{},"silent":{},"atomic":{}}}"#, json::quote(&node.name), modifier, node.is_silent(), node.is_atomic())
+            })
+            .collect();
+        let edges: Vec<String> = self.edges.iter().map(|(from, to)| format!(r#"{{"from":{},"to":{}}}"#, json::quote(from), json::quote(to))).collect();
+
+        format!(r#"{{"nodes":[{}],"edges":[{}]}}"#, nodes.join(","), edges.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const GRAMMAR: &str = indoc::indoc! {r#"
+        file  = { SOI ~ item* ~ EOI }
+        item  = { key ~ "=" ~ value | key }
+        key   = @{ ASCII_ALPHA+ }
+        value = ${ key | other }
+        other = !{ "x" }
+        WHITESPACE = _{ " " }
+    "#};
+
+    #[test]
+    fn test_graph() {
+        let graph = Graph::new(GRAMMAR, None).unwrap();
+        assert_eq!(vec!["file", "item", "key", "value", "other", "WHITESPACE"], graph.nodes.iter().map(|node| node.name.as_str()).collect::<Vec<_>>());
+        assert_eq!(vec![("file", "item"), ("item", "key"), ("item", "value"), ("value", "key"), ("value", "other")], graph.edges.iter().map(|(from, to)| (from.as_str(), to.as_str())).collect::<Vec<_>>());
+
+        let graph = Graph::new(GRAMMAR, Some("value")).unwrap();
+        assert_eq!(vec!["key", "value", "other"], graph.nodes.iter().map(|node| node.name.as_str()).collect::<Vec<_>>());
+        assert_eq!("rule missing is not defined", Graph::new(GRAMMAR, Some("missing")).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn test_formats() {
+        let graph = Graph::new(GRAMMAR, Some("value")).unwrap();
+
+        let expected = indoc::indoc! {r#"
+            digraph grammar {
+                "key" [modifier="@", shape=box];
+                "value" [modifier="$", shape=box, style=rounded];
+                "other" [modifier="!"];
+                "value" -> "key";
+                "value" -> "other";
+            }
+        "#};
+        assert_eq!(expected, graph.to_dot());
+
+        let expected = indoc::indoc! {r#"
+            flowchart LR
+                classDef silent stroke-dasharray: 5 5
+                classDef atomic stroke-width: 3px
+                classDef compound_atomic stroke-width: 3px, stroke-dasharray: 5 5
+                classDef non_atomic stroke-width: 0.5px
+                n0["key"]:::atomic
+                n1["value"]:::compound_atomic
+                n2["other"]:::non_atomic
+                n1 --> n0
+                n1 --> n2
+        "#};
+        assert_eq!(expected, graph.to_mermaid());
+
+        let graph = Graph::new("a = { b }\nb = _{ \"b\" }", None).unwrap();
+        assert_eq!(r#"{"nodes":[{"name":"a","modifier":null,"silent":false,"atomic":false},{"name":"b","modifier":"_","silent":true,"atomic":false}],"edges":[{"from":"a","to":"b"}]}"#, graph.to_json());
+    }
+}
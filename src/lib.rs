@@ -8,6 +8,14 @@ extern crate quote;
 
 #[cfg(test)]
 macro_rules! expect_correction {
+    ($config:expr => $source:expr, $expected:expr $(,)?) => {
+        let source = indoc::indoc! { $source };
+        let expected = indoc::indoc! { $expected };
+
+        let fmt = crate::Formatter::with_config(source, $config);
+
+        pretty_assertions::assert_eq!(fmt.format().unwrap().trim_end(), expected.trim_end())
+    };
     ($source:expr, $expected:expr,) => {
         let source = indoc::indoc! { $source };
         let expected = indoc::indoc! { $expected };
@@ -22,27 +30,59 @@ macro_rules! expect_correction {
 }
 
 #[macro_use]
-
 mod error;
+mod ast;
 mod comment;
+mod config;
 pub mod formatter;
+pub mod graph;
+// Only public for the binary, not a part of the API.
+#[doc(hidden)]
+pub mod json;
+pub mod lint;
 mod newline;
 mod node;
+pub mod rename;
+mod rust;
+mod stack;
+pub mod stats;
 
+pub use config::{BlockCommentStyle, ChoiceStyle, CommentStyle, Config, DocSpacing, NewlineStyle, RuleSpacing, SequenceStyle, TagSpacing};
 pub use error::{PestError, PestResult};
+pub use lint::{Diagnostic, Level, Lint, LintConfig, Linter};
+pub use rename::{rename_rule, rename_rust, RulePaths, RustRename};
 pub(crate) use node::*;
 
 pub struct Formatter<'a> {
+    /// The input without the UTF-8 BOM
     input: &'a str,
 
+    /// Whether the input starts with a UTF-8 BOM
+    bom: bool,
+
     /// Indent space size
     indent: usize,
+
+    config: Config,
 }
 
 impl<'a> Formatter<'a> {
     /// Create new formatter
     pub fn new(input: &'a str) -> Formatter<'a> {
-        Self { input, indent: 4 }
+        Self::with_config(input, Config::default())
+    }
+
+    /// Create new formatter with the given options
+    pub fn with_config(input: &'a str, config: Config) -> Formatter<'a> {
+        let bom = input.starts_with('\u{feff}');
+        let input = input.trim_start_matches('\u{feff}');
+
+        Self { input, bom, indent: 4, config }
+    }
+
+    /// Returns the indent of one level, by the `hard_tabs` option.
+    pub(crate) fn indent_str(&self) -> String {
+        if self.config.hard_tabs { "\t".to_string() } else { " ".repeat(self.indent) }
     }
 
     /// Returns the str of the range in self.input, return empty str if the
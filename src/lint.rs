@@ -0,0 +1,1024 @@
+//! Lints for the mistakes in grammars, which the formatting doesn't catch.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use pest::error::{Error, InputLocation, LineColLocation};
+use pest_meta::{parser, validator};
+use toml::Value;
+
+use crate::{
+    ast::{Expr, ExprKind, Grammar, RuleDef, RuleType},
+    config::{parse_string_list, parse_value},
+    rename::rename_rule,
+    rust::Source,
+    stack::{self, ProblemKind},
+    PestError, PestResult,
+};
+
+/// The lints, see `Lint::name` for the names in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A reference to a rule that is not defined and is not a builtin rule.
+    UndefinedRule,
+    /// A rule that is not reachable from the entry rules, or without entry rules, a silent rule that is
+    /// not referenced by any other rule.
+    UnusedRule,
+    /// A rule that is defined more than once, or redefines a pest keyword.
+    DuplicateRule,
+    /// A rule that can reach itself without consuming any input.
+    LeftRecursion,
+    /// An expression inside a repetition that can match the empty string, so it repeats infinitely.
+    EmptyRepetition,
+    /// An alternative of an ordered choice that is never matched, because an earlier alternative always
+    /// matches first, e.g.: `"a" | "ab"`.
+    ShadowedAlternative,
+    /// A rule that is not silent and has no `///` doc comment, so its variant of the generated `Rule` enum is
+    /// undocumented. Allowed by default.
+    MissingDocs,
+    /// A rule that does not follow the naming convention: SCREAMING_CASE for the builtin-like rules, e.g.:
+    /// `WHITESPACE`, snake_case for the others, and the silent rules start with the `silent_prefix`. Allowed by
+    /// default.
+    NamingConvention,
+    /// A tag used more than once in a sequence of a rule, e.g.: `#a = b ~ #a = c`.
+    DuplicateTag,
+    /// A tag on an expression that produces no pair, e.g.: a literal or a silent rule, or a tag in an atomic
+    /// rule. pest puts the tag on the previous pair then.
+    IneffectiveTag,
+    /// A tag that is not in the case of most of the tags of the grammar, e.g.: `#fooBar` among `#foo_bar`.
+    InconsistentTagNaming,
+    /// A `POP`, `DROP`, `PEEK` or a rule using them, without a value pushed before on some path, e.g.:
+    /// `PUSH("a")? ~ POP`.
+    StackUnderflow,
+    /// A repetition that pushes more values than it pops, so the stack grows with the input, e.g.:
+    /// `(PUSH("a") ~ "b")*`.
+    UnbalancedPush,
+}
+
+impl Lint {
+    pub const ALL: &'static [Lint] = &[Lint::UndefinedRule, Lint::UnusedRule, Lint::DuplicateRule, Lint::LeftRecursion, Lint::EmptyRepetition, Lint::ShadowedAlternative, Lint::MissingDocs, Lint::NamingConvention, Lint::DuplicateTag, Lint::IneffectiveTag, Lint::InconsistentTagNaming, Lint::StackUnderflow, Lint::UnbalancedPush];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UndefinedRule => "undefined_rule",
+            Lint::UnusedRule => "unused_rule",
+            Lint::DuplicateRule => "duplicate_rule",
+            Lint::LeftRecursion => "left_recursion",
+            Lint::EmptyRepetition => "empty_repetition",
+            Lint::ShadowedAlternative => "shadowed_alternative",
+            Lint::MissingDocs => "missing_docs",
+            Lint::NamingConvention => "naming_convention",
+            Lint::DuplicateTag => "duplicate_tag",
+            Lint::IneffectiveTag => "ineffective_tag",
+            Lint::InconsistentTagNaming => "inconsistent_tag_naming",
+            Lint::StackUnderflow => "stack_underflow",
+            Lint::UnbalancedPush => "unbalanced_push",
+        }
+    }
+
+    pub fn default_level(&self) -> Level {
+        match self {
+            Lint::MissingDocs | Lint::NamingConvention => Level::Allow,
+            Lint::UnusedRule | Lint::ShadowedAlternative | Lint::DuplicateTag | Lint::IneffectiveTag | Lint::InconsistentTagNaming | Lint::StackUnderflow | Lint::UnbalancedPush => Level::Warn,
+            Lint::UndefinedRule | Lint::DuplicateRule | Lint::LeftRecursion | Lint::EmptyRepetition => Level::Deny,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Lint::ALL.iter().find(|lint| lint.name() == s) {
+            Some(lint) => Ok(*lint),
+            None => Err(format!("unknown lint `{}`", s)),
+        }
+    }
+}
+
+/// The level of a lint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// The lint is not reported.
+    Allow,
+    /// Reported as a warning.
+    Warn,
+    /// Reported as an error, the CLI exits with a failure.
+    Deny,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Allow => f.write_str("allow"),
+            Level::Warn => f.write_str("warning"),
+            Level::Deny => f.write_str("error"),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Level::Allow),
+            "warn" => Ok(Level::Warn),
+            "deny" => Ok(Level::Deny),
+            _ => Err(format!("unknown value `{}`, expected one of `allow`, `warn`, `deny`", s)),
+        }
+    }
+}
+
+/// Lint options.
+///
+/// The CLI reads them from the `[pest]` table of `rustfmt.toml`, the levels from the `[pest.lints]` table,
+/// e.g.: `unused_rule = "allow"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    /// The levels of the lints, the lints not in the map have their default level.
+    pub levels: HashMap<Lint, Level>,
+    /// The rules used by the parser, the other rules must be reachable from them.
+    /// `WHITESPACE` and `COMMENT` are always used.
+    pub entry_rules: Vec<String>,
+    /// The patterns of the rules that need no doc comments, `*` matches any characters, e.g.: `*_op`.
+    pub missing_docs_exempt: Vec<String>,
+    /// The patterns of the builtin-like rules, which are SCREAMING_CASE, as `WHITESPACE` and `COMMENT` always.
+    pub screaming_rules: Vec<String>,
+    /// The prefix of the silent rules, e.g.: `_`.
+    pub silent_prefix: Option<String>,
+}
+
+impl LintConfig {
+    /// Read the options from the `[pest]` table.
+    pub fn from_toml(table: &Value) -> PestResult<LintConfig> {
+        let mut config = LintConfig::default();
+
+        if let Some(value) = table.get("entry_rules") {
+            config.entry_rules = parse_string_list("entry_rules", value)?;
+        }
+        if let Some(value) = table.get("missing_docs_exempt") {
+            config.missing_docs_exempt = parse_string_list("missing_docs_exempt", value)?;
+        }
+        if let Some(value) = table.get("screaming_rules") {
+            config.screaming_rules = parse_string_list("screaming_rules", value)?;
+        }
+        if let Some(value) = table.get("silent_prefix") {
+            match value.as_str() {
+                Some(prefix) => config.silent_prefix = Some(prefix.to_string()),
+                None => return Err(PestError::InvalidConfig(format!("silent_prefix: expected a string, found `{}`", value))),
+            }
+        }
+
+        if let Some(lints) = table.get("lints") {
+            let lints = match lints.as_table() {
+                Some(lints) => lints,
+                None => return Err(PestError::InvalidConfig(format!("lints: expected a table, found `{}`", lints))),
+            };
+
+            for (key, value) in lints {
+                let lint = key.parse().map_err(|e| PestError::InvalidConfig(format!("lints: {}", e)))?;
+                config.levels.insert(lint, parse_value(&format!("lints.{}", key), value)?);
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or_else(|| lint.default_level())
+    }
+}
+
+/// A related location of a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+/// A problem found by a lint, at the line and column (1-based).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub level: Level,
+    pub message: String,
+    pub line: usize,
+    pub col: usize,
+    pub notes: Vec<Note>,
+}
+
+pub struct Linter<'a> {
+    input: &'a str,
+    config: LintConfig,
+}
+
+impl<'a> Linter<'a> {
+    pub fn new(input: &'a str) -> Linter<'a> {
+        Linter::with_config(input, LintConfig::default())
+    }
+
+    pub fn with_config(input: &'a str, config: LintConfig) -> Linter<'a> {
+        Linter { input: input.strip_prefix('\u{feff}').unwrap_or(input), config }
+    }
+
+    /// Returns the diagnostics of the lints that are not allowed, sorted by location.
+    pub fn lint(&self) -> PestResult<Vec<Diagnostic>> {
+        let grammar = Grammar::parse(self.input)?;
+        let mut diagnostics = vec![];
+
+        self.check_unused(&grammar, &mut diagnostics);
+        self.check_shadowed(&grammar, &mut diagnostics);
+        self.check_missing_docs(&grammar, &mut diagnostics);
+        self.check_naming(&grammar, &mut diagnostics);
+        self.check_tags(&grammar, &mut diagnostics);
+        self.check_stack(&grammar, &mut diagnostics);
+        self.validate(&grammar, &mut diagnostics);
+
+        diagnostics.retain(|diagnostic| diagnostic.level != Level::Allow);
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.col));
+        Ok(diagnostics)
+    }
+
+    fn diagnostic(&self, lint: Lint, message: String, (line, col): (usize, usize)) -> Diagnostic {
+        Diagnostic { lint, level: self.config.level(lint), message, line, col, notes: vec![] }
+    }
+
+    fn check_unused(&self, grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+        for rule in self.unused_rules(grammar) {
+            let message = match self.config.entry_rules.is_empty() {
+                true => format!("rule {} is never used", rule.name),
+                false => format!("rule {} is not reachable from the entry rules", rule.name),
+            };
+            diagnostics.push(self.diagnostic(Lint::UnusedRule, message, (rule.span.line, rule.span.col)));
+        }
+    }
+
+    fn unused_rules<'g>(&self, grammar: &'g Grammar) -> Vec<&'g RuleDef> {
+        // WHITESPACE and COMMENT are used implicitly
+        let implicit = ["WHITESPACE", "COMMENT"];
+
+        if self.config.entry_rules.is_empty() {
+            let is_used = |rule: &RuleDef| grammar.rules.iter().filter(|other| other.name != rule.name).any(|other| other.references.contains(&rule.name));
+            grammar.rules.iter().filter(|rule| rule.ty == RuleType::Silent && !implicit.contains(&rule.name.as_str()) && !is_used(rule)).collect()
+        } else {
+            let reachable = grammar.reachable(self.config.entry_rules.iter().map(String::as_str).chain(implicit));
+            grammar.rules.iter().filter(|rule| !reachable.contains(rule.name.as_str())).collect()
+        }
+    }
+
+    /// Fix the problems of the lints that are not allowed: remove the unused rules with their doc comments,
+    /// add a placeholder doc comment to the rules without one, and rename the rules that do not follow the
+    /// naming convention with their references. Returns the fixed source, which should be formatted again.
+    pub fn fix(&self) -> PestResult<String> {
+        Ok(self.fix_renames()?.0)
+    }
+
+    /// Same as `fix`, with the rules renamed for the naming convention as `(old, new)`, in order. The renames
+    /// are only done in the grammar, the `Rule::old` paths of the Rust sources must be renamed too.
+    pub fn fix_renames(&self) -> PestResult<(String, Vec<(String, String)>)> {
+        let mut source = self.input.to_string();
+        if self.config.level(Lint::UnusedRule) != Level::Allow {
+            source = self.remove_unused(source)?;
+        }
+        if self.config.level(Lint::MissingDocs) != Level::Allow {
+            source = self.add_docs(source)?;
+        }
+        let mut renames = vec![];
+        if self.config.level(Lint::NamingConvention) != Level::Allow {
+            source = self.rename_rules(source, &mut renames)?;
+        }
+
+        Ok((source, renames))
+    }
+
+    /// Remove the unused rules, until there are none.
+    fn remove_unused(&self, mut source: String) -> PestResult<String> {
+        loop {
+            let grammar = Grammar::parse(&source)?;
+            let mut ranges: Vec<(usize, usize)> = self.unused_rules(&grammar).iter().map(|rule| line_range(&source, rule.start, rule.end)).collect();
+            if ranges.is_empty() {
+                return Ok(source);
+            }
+
+            ranges.sort();
+            for (start, end) in ranges.into_iter().rev() {
+                source.replace_range(start..end, "");
+            }
+        }
+    }
+
+    fn check_missing_docs(&self, grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+        for rule in self.undocumented_rules(grammar) {
+            diagnostics.push(self.diagnostic(Lint::MissingDocs, format!("rule {} has no doc comment", rule.name), (rule.span.line, rule.span.col)));
+        }
+    }
+
+    fn undocumented_rules<'g>(&self, grammar: &'g Grammar) -> Vec<&'g RuleDef> {
+        let is_exempt = |rule: &RuleDef| self.config.missing_docs_exempt.iter().any(|pattern| wildcard_match(pattern, &rule.name));
+
+        // Without doc comments, the rule starts at the identifier.
+        grammar.rules.iter().filter(|rule| rule.ty != RuleType::Silent && rule.start == rule.span.start && !is_exempt(rule)).collect()
+    }
+
+    /// Insert a placeholder doc comment before the rules without one.
+    fn add_docs(&self, mut source: String) -> PestResult<String> {
+        let grammar = Grammar::parse(&source)?;
+        for rule in self.undocumented_rules(&grammar).into_iter().rev() {
+            let line_start = source[..rule.start].rfind('\n').map_or(0, |i| i + 1);
+            let indent = &source[line_start..rule.start];
+            let doc = match indent.trim().is_empty() {
+                true => format!("{}/// TODO: document the rule\n", indent),
+                false => "\n/// TODO: document the rule\n".to_string(),
+            };
+            let at = if indent.trim().is_empty() { line_start } else { rule.start };
+            source.insert_str(at, &doc);
+        }
+
+        Ok(source)
+    }
+
+    fn check_naming(&self, grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+        for rule in &grammar.rules {
+            if let Some((problems, name)) = self.naming_problems(rule) {
+                // The name is only suggested if `--fix` can rename the rule to it.
+                let message = match rename_rule(self.input, &rule.name, &name) {
+                    Ok(_) => format!("rule {} is not {}, rename it to {}", rule.name, problems.join(" and "), name),
+                    Err(e) => format!("rule {} is not {}, it cannot be renamed: {}", rule.name, problems.join(" and "), e),
+                };
+                diagnostics.push(self.diagnostic(Lint::NamingConvention, message, (rule.span.line, rule.span.col)));
+            }
+        }
+    }
+
+    /// The conventions that the name of the rule does not follow, and the name that follows them.
+    fn naming_problems(&self, rule: &RuleDef) -> Option<(Vec<String>, String)> {
+        let screaming = ["WHITESPACE", "COMMENT"].contains(&rule.name.as_str()) || self.config.screaming_rules.iter().any(|pattern| wildcard_match(pattern, &rule.name));
+        let prefix = match &self.config.silent_prefix {
+            Some(prefix) if rule.ty == RuleType::Silent && !screaming => prefix.as_str(),
+            _ => "",
+        };
+
+        let mut problems = vec![];
+        let base = rule.name.strip_prefix(prefix).unwrap_or_else(|| {
+            problems.push(format!("prefixed with `{}`", prefix));
+            &rule.name
+        });
+        let (case, cased) = match screaming {
+            true => ("SCREAMING_CASE", snake_case(base).to_uppercase()),
+            false => ("snake_case", snake_case(base)),
+        };
+        if cased != base {
+            problems.insert(0, case.to_string());
+        }
+
+        match problems.is_empty() {
+            true => None,
+            false => Some((problems, format!("{}{}", prefix, cased))),
+        }
+    }
+
+    /// Rename the rules that do not follow the naming convention, unless the new name is taken: the rules left
+    /// are still reported by `check_naming`, with the reason.
+    fn rename_rules(&self, mut source: String, renames: &mut Vec<(String, String)>) -> PestResult<String> {
+        let grammar = Grammar::parse(&source)?;
+        for rule in &grammar.rules {
+            if let Some((_, name)) = self.naming_problems(rule) {
+                if let Ok(renamed) = rename_rule(&source, &rule.name, &name) {
+                    source = renamed;
+                    renames.push((rule.name.clone(), name));
+                }
+            }
+        }
+
+        Ok(source)
+    }
+
+    fn check_tags(&self, grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+        let mut tags = vec![];
+        for rule in &grammar.rules {
+            let mut duplicates = vec![];
+            tags.extend(collect_tags(&rule.expr, &mut duplicates));
+            for (first, tag) in duplicates {
+                let mut diagnostic = self.diagnostic(Lint::DuplicateTag, format!("tag #{} is used more than once in rule {}", tag_name(tag), rule.name), (tag.span.line, tag.span.col));
+                diagnostic.notes.push(Note { line: first.span.line, col: first.span.col, message: format!("#{} is first used here", tag_name(first)) });
+                diagnostics.push(diagnostic);
+            }
+
+            rule.expr.walk(&mut |expr| {
+                let tagged = match &expr.kind {
+                    ExprKind::Tag(_, tagged) => tagged,
+                    _ => return,
+                };
+                let message = if rule.ty == RuleType::Atomic {
+                    format!("tag #{} has no effect in atomic rule {}", tag_name(expr), rule.name)
+                } else if !produces_pairs(tagged, grammar, &mut HashSet::new()) {
+                    // The span of the tagged expression starts at the tag.
+                    let tagged = self.input[expr.span.start..expr.span.end].split_once('=').map_or("", |(_, tagged)| tagged.trim());
+                    format!("tag #{} has no effect, {} produces no pair", tag_name(expr), tagged)
+                } else {
+                    return;
+                };
+                diagnostics.push(self.diagnostic(Lint::IneffectiveTag, message, (expr.span.line, expr.span.col)));
+            });
+        }
+
+        // The case of most of the tags, the first one on a tie.
+        let cases: Vec<&str> = tags.iter().map(|tag| tag_case(tag_name(tag))).collect();
+        let count = |case: &str| cases.iter().filter(|other| **other == case).count();
+        if let Some(main) = cases.iter().copied().reduce(|main, case| if count(case) > count(main) { case } else { main }) {
+            for (tag, case) in tags.iter().zip(&cases).filter(|(_, case)| **case != main) {
+                let message = format!("tag #{} is {}, the other tags are {}", tag_name(tag), case, main);
+                diagnostics.push(self.diagnostic(Lint::InconsistentTagNaming, message, (tag.span.line, tag.span.col)));
+            }
+        }
+    }
+
+    fn check_stack(&self, grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+        for problem in stack::check(grammar, self.input) {
+            let lint = match problem.kind {
+                ProblemKind::Underflow => Lint::StackUnderflow,
+                ProblemKind::UnbalancedPush => Lint::UnbalancedPush,
+            };
+            diagnostics.push(self.diagnostic(lint, problem.message, (problem.span.line, problem.span.col)));
+        }
+    }
+
+    fn check_shadowed(&self, grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+        for rule in &grammar.rules {
+            rule.expr.walk(&mut |expr| {
+                let alternatives = match &expr.kind {
+                    ExprKind::Choice(alternatives) => alternatives,
+                    _ => return,
+                };
+
+                for (i, later) in alternatives.iter().enumerate() {
+                    if let Some(earlier) = alternatives[..i].iter().find(|earlier| shadows(earlier, later)) {
+                        let (earlier_str, later_str) = (&self.input[earlier.span.start..earlier.span.end], &self.input[later.span.start..later.span.end]);
+                        let mut diagnostic = self.diagnostic(Lint::ShadowedAlternative, format!("alternative {} is never matched, {} always matches first", later_str, earlier_str), (later.span.line, later.span.col));
+                        diagnostic.notes.push(Note { line: earlier.span.line, col: earlier.span.col, message: format!("{} is matched here", earlier_str) });
+                        diagnostics.push(diagnostic);
+                    }
+                }
+            });
+        }
+    }
+
+    /// The checks of the validator of `pest_meta`.
+    fn validate(&self, grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+        let pairs = match parser::parse(parser::Rule::grammar_rules, self.input) {
+            Ok(pairs) => pairs,
+            // Parsed by `Grammar::parse` already, the grammar of `pest_meta` may be different.
+            Err(_) => return,
+        };
+
+        let definitions: Vec<_> = pairs.clone().filter(|pair| pair.as_rule() == parser::Rule::grammar_rule).filter_map(|pair| pair.into_inner().next()).filter(|pair| pair.as_rule() == parser::Rule::identifier).map(|pair| pair.as_span()).collect();
+        let called_rules: Vec<_> = pairs.clone().filter(|pair| pair.as_rule() == parser::Rule::grammar_rule).flat_map(|pair| pair.into_inner().flatten().skip(1).filter(|pair| pair.as_rule() == parser::Rule::identifier).map(|pair| pair.as_span())).collect();
+
+        for error in validator::validate_undefined(&definitions, &called_rules) {
+            diagnostics.push(self.diagnostic(Lint::UndefinedRule, error.variant.message().to_string(), start(&error)));
+        }
+        for error in validator::validate_pest_keywords(&definitions) {
+            diagnostics.push(self.diagnostic(Lint::DuplicateRule, error.variant.message().to_string(), start(&error)));
+        }
+        for error in validator::validate_already_defined(&definitions) {
+            let mut diagnostic = self.diagnostic(Lint::DuplicateRule, error.variant.message().to_string(), start(&error));
+            if let Some(first) = name(&error, self.input).and_then(|name| grammar.get(name)) {
+                diagnostic.notes.push(Note { line: first.span.line, col: first.span.col, message: format!("rule {} is first defined here", first.name) });
+            }
+            diagnostics.push(diagnostic);
+        }
+
+        if let Err(errors) = parser::consume_rules(pairs) {
+            for error in errors {
+                let message = error.variant.message().to_string();
+                let lint = if message.contains("left-recursive") {
+                    Lint::LeftRecursion
+                } else if message.contains("will repeat infinitely") {
+                    Lint::EmptyRepetition
+                } else if message.contains("following choices cannot be reached") {
+                    Lint::ShadowedAlternative
+                } else {
+                    continue;
+                };
+                diagnostics.push(self.diagnostic(lint, message, start(&error)));
+            }
+        }
+    }
+}
+
+/// Whether the `earlier` alternative always matches when the `later` one would, it must be a literal or
+/// a range, and the `later` one must start with a literal.
+fn shadows(earlier: &Expr, later: &Expr) -> bool {
+    let has_case = |s: &str| s.to_lowercase() != s.to_uppercase();
+    let first_char = |s: &str| s.chars().next();
+
+    match (untagged(earlier), leading_literal(later)) {
+        // An empty string always matches, which `pest_meta` reports.
+        (ExprKind::Str(a), _) | (ExprKind::Insens(a), _) if a.is_empty() => false,
+        (ExprKind::Str(a), Some(ExprKind::Str(b))) => b.starts_with(a.as_str()),
+        (ExprKind::Str(a), Some(ExprKind::Insens(b))) => !has_case(b) && b.starts_with(a.as_str()),
+        (ExprKind::Insens(a), Some(ExprKind::Str(b) | ExprKind::Insens(b))) => b.to_lowercase().starts_with(&a.to_lowercase()),
+        (ExprKind::Range(start, end), Some(ExprKind::Str(b))) => first_char(b).is_some_and(|c| (*start..=*end).contains(&c)),
+        (ExprKind::Range(start, end), Some(ExprKind::Insens(b))) => first_char(b).is_some_and(|c| !has_case(&c.to_string()) && (*start..=*end).contains(&c)),
+        _ => false,
+    }
+}
+
+fn untagged(expr: &Expr) -> &ExprKind {
+    match &expr.kind {
+        ExprKind::Tag(_, expr) => untagged(expr),
+        kind => kind,
+    }
+}
+
+/// The literal that the matches of the expression always start with.
+fn leading_literal(expr: &Expr) -> Option<&ExprKind> {
+    match untagged(expr) {
+        kind @ (ExprKind::Str(_) | ExprKind::Insens(_)) => Some(kind),
+        ExprKind::Seq(exprs) => exprs.first().and_then(leading_literal),
+        ExprKind::Push(expr) | ExprKind::RepOnce(expr) => leading_literal(expr),
+        ExprKind::RepExact(expr, min) | ExprKind::RepMin(expr, min) | ExprKind::RepMinMax(expr, min, _) if *min > 0 => leading_literal(expr),
+        _ => None,
+    }
+}
+
+/// The tags of the expression, in the source order. The tags used again in a sequence are pushed to
+/// `duplicates` with the first tag, the alternatives of a choice may use the same tag.
+fn collect_tags<'a>(expr: &'a Expr, duplicates: &mut Vec<(&'a Expr, &'a Expr)>) -> Vec<&'a Expr> {
+    match &expr.kind {
+        ExprKind::Tag(_, tagged) => {
+            let mut tags = vec![expr];
+            tags.extend(collect_tags(tagged, duplicates));
+            tags
+        }
+        ExprKind::Seq(exprs) => {
+            let mut tags: Vec<&Expr> = vec![];
+            for expr in exprs {
+                let new_tags = collect_tags(expr, duplicates);
+                for tag in &new_tags {
+                    if let Some(first) = tags.iter().find(|first| tag_name(first) == tag_name(tag)) {
+                        duplicates.push((first, tag));
+                    }
+                }
+                tags.extend(new_tags);
+            }
+            tags
+        }
+        _ => expr.children().into_iter().flat_map(|child| collect_tags(child, duplicates)).collect(),
+    }
+}
+
+fn tag_name(expr: &Expr) -> &str {
+    match &expr.kind {
+        ExprKind::Tag(name, _) => name.trim_start_matches('#'),
+        _ => "",
+    }
+}
+
+/// The case of the name: snake_case, SCREAMING_CASE, PascalCase or camelCase.
+fn tag_case(name: &str) -> &'static str {
+    let name = name.trim_start_matches('_');
+    let has_upper = name.chars().any(char::is_uppercase);
+    let has_lower = name.chars().any(char::is_lowercase);
+    match (has_upper, has_lower) {
+        (false, _) => "snake_case",
+        (true, false) => "SCREAMING_CASE",
+        _ if name.starts_with(char::is_uppercase) => "PascalCase",
+        _ => "camelCase",
+    }
+}
+
+/// Whether the expression can produce pairs: it references a rule which is not silent, or a silent rule
+/// that does.
+fn produces_pairs<'a>(expr: &'a Expr, grammar: &'a Grammar, visiting: &mut HashSet<&'a str>) -> bool {
+    match &expr.kind {
+        ExprKind::Ident(name) => match grammar.get(name) {
+            Some(rule) if rule.ty == RuleType::Silent => visiting.insert(name) && produces_pairs(&rule.expr, grammar, visiting),
+            Some(_) => true,
+            // Builtin or undefined
+            None => false,
+        },
+        ExprKind::PosPred(_) | ExprKind::NegPred(_) => false,
+        _ => expr.children().into_iter().any(|child| produces_pairs(child, grammar, visiting)),
+    }
+}
+
+/// Convert the name to snake_case, e.g.: `HTTPRequest` to `http_request`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len());
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// Whether the name matches the pattern, where `*` matches any characters.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let name = match name.strip_prefix(prefix) {
+                Some(name) => name,
+                None => return false,
+            };
+            (0..=name.len()).filter(|i| name.is_char_boundary(*i)).any(|i| wildcard_match(rest, &name[i..]))
+        }
+    }
+}
+
+/// Extend the range to the whole lines, if there is only whitespace or a comment around it.
+fn line_range(source: &str, start: usize, end: usize) -> (usize, usize) {
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let start = if source[line_start..start].trim().is_empty() { line_start } else { start };
+
+    let line_end = source[end..].find('\n').map_or(source.len(), |i| end + i + 1);
+    let rest = source[end..line_end].trim();
+    let end = if rest.is_empty() || rest.starts_with("//") { line_end } else { end };
+
+    (start, end)
+}
+
+/// A `Rule::name` path of a Rust source, see `rust_rule_references`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleReference {
+    pub name: String,
+    /// The `#[grammar = "..."]` paths of the parsers of the `Rule`, `None` if the path is not resolved to a
+    /// parser, e.g.: a `Rule` imported from another file
+    pub grammars: Option<Vec<String>>,
+}
+
+/// The `Rule::name` paths of the Rust source, e.g.: `parse(Rule::file, input)`, sorted.
+///
+/// The comments and the literals are skipped, and the `Rule` of a path is resolved like in `rename_rust`: it is
+/// the one of the parsers in the closest module around it.
+pub fn rust_rule_references(source: &str) -> Vec<RuleReference> {
+    let rust = Source::new(source);
+
+    let mut references: Vec<RuleReference> = rust
+        .all_rule_paths()
+        .into_iter()
+        .map(|(start, end, qualified)| {
+            let parsers = if qualified { vec![] } else { rust.parser_at(start) };
+            let grammars = Some(parsers.iter().flat_map(|parser| parser.files.clone()).collect()).filter(|_| !parsers.is_empty());
+            RuleReference { name: source[start..end].to_string(), grammars }
+        })
+        .collect();
+
+    references.sort_by(|a, b| (&a.name, &a.grammars).cmp(&(&b.name, &b.grammars)));
+    references.dedup();
+    references
+}
+
+/// The source of the span of the error.
+fn name<'i>(error: &Error<parser::Rule>, input: &'i str) -> Option<&'i str> {
+    match error.location {
+        InputLocation::Span((start, end)) => input.get(start..end),
+        InputLocation::Pos(_) => None,
+    }
+}
+
+/// The line and column of the start of the error.
+fn start(error: &Error<parser::Rule>) -> (usize, usize) {
+    match error.line_col {
+        LineColLocation::Pos(pos) | LineColLocation::Span(pos, _) => pos,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(source: &str) -> Vec<(Lint, Level, usize, usize)> {
+        Linter::new(source).lint().unwrap().into_iter().map(|d| (d.lint, d.level, d.line, d.col)).collect()
+    }
+
+    #[test]
+    fn test_undefined_and_duplicate_rule() {
+        let source = indoc::indoc! {r#"
+            a = { b ~ ASCII_DIGIT ~ EOI }
+            a = { "a" }
+            ANY = { "x" }
+        "#};
+        let diagnostics = Linter::new(source).lint().unwrap();
+        assert_eq!(3, diagnostics.len());
+
+        assert_eq!((Lint::UndefinedRule, Level::Deny, 1, 7), (diagnostics[0].lint, diagnostics[0].level, diagnostics[0].line, diagnostics[0].col));
+        assert_eq!("rule b is undefined", diagnostics[0].message);
+        assert_eq!((Lint::DuplicateRule, 2, 1), (diagnostics[1].lint, diagnostics[1].line, diagnostics[1].col));
+        assert_eq!("rule a already defined", diagnostics[1].message);
+        assert_eq!(vec![Note { line: 1, col: 1, message: "rule a is first defined here".to_string() }], diagnostics[1].notes);
+        assert_eq!((Lint::DuplicateRule, 3, 1), (diagnostics[2].lint, diagnostics[2].line, diagnostics[2].col));
+        assert_eq!("ANY is a pest keyword", diagnostics[2].message);
+    }
+
+    #[test]
+    fn test_unused_rule() {
+        let diagnostics = lint(indoc::indoc! {r#"
+            main = { a ~ b }
+            a = _{ "a" }
+            b = { "b" }
+            /// Only used by itself
+            c = _{ "(" ~ c? ~ ")" }
+            WHITESPACE = _{ " " }
+        "#});
+        assert_eq!(vec![(Lint::UnusedRule, Level::Warn, 5, 1)], diagnostics);
+    }
+
+    #[test]
+    fn test_unused_rule_with_entry_rules() {
+        let source = indoc::indoc! {r#"
+            //! Grammar doc
+            file = { SOI ~ item* ~ EOI }
+            item = { key | value }
+
+            /// Key
+            /// of item
+            key = @{ ASCII_ALPHA+ }
+            value = _{ ASCII_DIGIT+ }
+            /// Only used by unused rules
+            dead = { "dead" ~ dead_inner } // trailing
+            dead_inner = _{ "x" }
+
+            WHITESPACE = _{ " " ~ space }
+            space = _{ "\t" }
+        "#};
+
+        let config = LintConfig { entry_rules: vec!["file".to_string()], ..Default::default() };
+        let diagnostics = Linter::with_config(source, config.clone()).lint().unwrap();
+        let diagnostics: Vec<(&str, usize)> = diagnostics.iter().map(|d| (d.message.as_str(), d.line)).collect();
+        assert_eq!(vec![("rule dead is not reachable from the entry rules", 10), ("rule dead_inner is not reachable from the entry rules", 11)], diagnostics);
+
+        let expected = indoc::indoc! {r#"
+            //! Grammar doc
+            file = { SOI ~ item* ~ EOI }
+            item = { key | value }
+
+            /// Key
+            /// of item
+            key = @{ ASCII_ALPHA+ }
+            value = _{ ASCII_DIGIT+ }
+
+            WHITESPACE = _{ " " ~ space }
+            space = _{ "\t" }
+        "#};
+        assert_eq!(expected, Linter::with_config(source, config).fix().unwrap());
+
+        // Without entry rules, the silent rules that are only used by the removed rules are removed too.
+        // The doc comments before a blank line belong to the next rule, as in pest.
+        let source = "a = { b }\n/// Doc\n\n/// Doc of b\nb = _{ c }\nc = _{ \"c\" }\n";
+        let config = LintConfig { entry_rules: vec![], ..Default::default() };
+        assert_eq!(source, Linter::with_config(source, config.clone()).fix().unwrap());
+        let source = "a = { \"a\" }\n/// Doc\n\n/// Doc of b\nb = _{ c }\nc = _{ \"c\" }\n";
+        assert_eq!("a = { \"a\" }\n", Linter::with_config(source, config).fix().unwrap());
+    }
+
+    #[test]
+    fn test_rust_rule_references() {
+        let source = r#"
+            #[derive(Parser)]
+            #[grammar = "grammar.pest"]
+            struct GrammarParser;
+
+            // Rule::comment
+            let pairs = GrammarParser::parse(Rule::file, "Rule::string")?;
+            match pair.as_rule() {
+                Rule::key | Rule::value => (),
+                MyRule::other => (),
+                Rule::key => (),
+                other::Rule::key => (),
+                _ => unreachable!(),
+            }
+
+            mod inline {
+                #[derive(Parser)]
+                #[grammar_inline = "a = { \"a\" }"]
+                struct InlineParser;
+
+                const _: Rule = Rule::a;
+            }
+        "#;
+        let reference = |name: &str, grammars: Option<&[&str]>| RuleReference { name: name.to_string(), grammars: grammars.map(|grammars| grammars.iter().map(|grammar| grammar.to_string()).collect()) };
+        let expected = vec![reference("a", Some(&[])), reference("file", Some(&["grammar.pest"])), reference("key", None), reference("key", Some(&["grammar.pest"])), reference("value", Some(&["grammar.pest"]))];
+        assert_eq!(expected, rust_rule_references(source));
+    }
+
+    #[test]
+    fn test_left_recursion_and_empty_repetition() {
+        let diagnostics = Linter::new("a = { a ~ \"a\" | \"b\" }\nb = { (\"b\"?)* }\n").lint().unwrap();
+        assert_eq!(2, diagnostics.len());
+        assert_eq!((Lint::LeftRecursion, 1, 7), (diagnostics[0].lint, diagnostics[0].line, diagnostics[0].col));
+        assert!(diagnostics[0].message.starts_with("rule a is left-recursive (a -> a)"), "{}", diagnostics[0].message);
+        assert_eq!((Lint::EmptyRepetition, 2, 7), (diagnostics[1].lint, diagnostics[1].line, diagnostics[1].col));
+        assert_eq!("expression inside repetition cannot fail and will repeat infinitely", diagnostics[1].message);
+    }
+
+    #[test]
+    fn test_shadowed_alternative() {
+        let source = indoc::indoc! {r#"
+            keyword = { "in" | "int" | ("a" | "b") | "i" ~ "f" | "x" | "y" }
+            insensitive = { ^"select" | "SELECT" | ^"SeLeCtEd" | "sel" | "s" ~ ^"EL" | ^"sel" }
+            tagged = { #a = "a" | "a"+ | "a"? | "ab"* }
+            range = { 'a'..'z' | "b" | ^"c" | "-" | ^"-1" | ^"." }
+            optional = { "x"? | "y" }
+        "#};
+        let diagnostics = Linter::new(source).lint().unwrap();
+        let diagnostics: Vec<(Lint, &str, usize, usize)> = diagnostics.iter().map(|d| (d.lint, d.message.as_str(), d.line, d.col)).collect();
+        assert_eq!(vec![(Lint::ShadowedAlternative, r#"alternative "int" is never matched, "in" always matches first"#, 1, 20), (Lint::ShadowedAlternative, r#"alternative "SELECT" is never matched, ^"select" always matches first"#, 2, 29), (Lint::ShadowedAlternative, r#"alternative ^"SeLeCtEd" is never matched, ^"select" always matches first"#, 2, 40), (Lint::IneffectiveTag, r#"tag #a has no effect, "a" produces no pair"#, 3, 12), (Lint::ShadowedAlternative, r#"alternative "a"+ is never matched, #a = "a" always matches first"#, 3, 23), (Lint::ShadowedAlternative, "expression cannot fail; following choices cannot be reached", 3, 30), (Lint::ShadowedAlternative, r#"alternative "b" is never matched, 'a'..'z' always matches first"#, 4, 22), (Lint::ShadowedAlternative, r#"alternative ^"-1" is never matched, "-" always matches first"#, 4, 41), (Lint::ShadowedAlternative, "expression cannot fail; following choices cannot be reached", 5, 14),], diagnostics);
+
+        let diagnostics = Linter::new("a = { \"a\" | \"ab\" }").lint().unwrap();
+        assert_eq!(vec![Note { line: 1, col: 7, message: r#""a" is matched here"#.to_string() }], diagnostics[0].notes);
+    }
+
+    #[test]
+    fn test_missing_docs() {
+        let source = indoc::indoc! {r#"
+            /// A file
+            file = { SOI ~ item* ~ EOI }
+            item = { key ~ "=" ~ value }
+            key = @{ ASCII_ALPHA+ }
+            value = _{ ASCII_DIGIT+ }
+            WHITESPACE = { " " }
+            add_op = { "+" } sub_op = { "-" }
+        "#};
+        assert!(lint(source).is_empty());
+
+        let mut config = LintConfig { levels: HashMap::from([(Lint::MissingDocs, Level::Warn)]), ..Default::default() };
+        let diagnostics = Linter::with_config(source, config.clone()).lint().unwrap();
+        let diagnostics: Vec<(&str, usize)> = diagnostics.iter().map(|d| (d.message.as_str(), d.line)).collect();
+        assert_eq!(vec![("rule item has no doc comment", 3), ("rule key has no doc comment", 4), ("rule WHITESPACE has no doc comment", 6), ("rule add_op has no doc comment", 7), ("rule sub_op has no doc comment", 7)], diagnostics);
+
+        config.missing_docs_exempt = vec!["WHITESPACE".to_string(), "*_op".to_string()];
+        let expected = indoc::indoc! {r#"
+            /// A file
+            file = { SOI ~ item* ~ EOI }
+            /// TODO: document the rule
+            item = { key ~ "=" ~ value }
+            /// TODO: document the rule
+            key = @{ ASCII_ALPHA+ }
+            value = _{ ASCII_DIGIT+ }
+            WHITESPACE = { " " }
+            add_op = { "+" } sub_op = { "-" }
+        "#};
+        let fixed = Linter::with_config(source, config.clone()).fix().unwrap();
+        assert_eq!(expected, fixed);
+        assert!(Linter::with_config(&fixed, config).lint().unwrap().is_empty());
+
+        let config = LintConfig { levels: HashMap::from([(Lint::MissingDocs, Level::Warn)]), ..Default::default() };
+        // A rule after another on the same line
+        assert_eq!("/// A\na = { \"a\" } \n/// TODO: document the rule\nb = { \"b\" }", Linter::with_config("/// A\na = { \"a\" } b = { \"b\" }", config).fix().unwrap());
+    }
+
+    #[test]
+    fn test_naming_convention() {
+        let source = indoc::indoc! {r#"
+            file = { SOI ~ keyValue* ~ EOI }
+            keyValue = { Key ~ "=" ~ ws ~ value }
+            Key = @{ ASCII_ALPHA+ }
+            value = _{ _digits | "x" }
+            _digits = _{ ASCII_DIGIT+ }
+            ws = _{ " " }
+            WHITESPACE = _{ " " }
+            Newline = { "\n" }
+            value_2 = { "2" }
+        "#};
+        assert!(lint(source).is_empty());
+
+        let config = LintConfig { levels: HashMap::from([(Lint::NamingConvention, Level::Warn)]), screaming_rules: vec!["NEW*".to_string(), "Newline".to_string()], silent_prefix: Some("_".to_string()), ..Default::default() };
+        let diagnostics = Linter::with_config(source, config.clone()).lint().unwrap();
+        let diagnostics: Vec<(&str, usize)> = diagnostics.iter().map(|d| (d.message.as_str(), d.line)).collect();
+        assert_eq!(vec![("rule keyValue is not snake_case, rename it to key_value", 2), ("rule Key is not snake_case, rename it to key", 3), ("rule value is not prefixed with `_`, rename it to _value", 4), ("rule ws is not prefixed with `_`, rename it to _ws", 6), ("rule Newline is not SCREAMING_CASE, it cannot be renamed: NEWLINE is a built-in rule", 8)], diagnostics);
+
+        let expected = indoc::indoc! {r#"
+            file = { SOI ~ key_value* ~ EOI }
+            key_value = { key ~ "=" ~ _ws ~ _value }
+            key = @{ ASCII_ALPHA+ }
+            _value = _{ _digits | "x" }
+            _digits = _{ ASCII_DIGIT+ }
+            _ws = _{ " " }
+            WHITESPACE = _{ " " }
+            Newline = { "\n" }
+            value_2 = { "2" }
+        "#};
+        // `NEWLINE` is a builtin rule, so `Newline` is not renamed and is still reported.
+        let renames = vec![("keyValue", "key_value"), ("Key", "key"), ("value", "_value"), ("ws", "_ws")].into_iter().map(|(old, new)| (old.to_string(), new.to_string())).collect();
+        assert_eq!((expected.to_string(), renames), Linter::with_config(source, config.clone()).fix_renames().unwrap());
+        let diagnostics = Linter::with_config(expected, config.clone()).lint().unwrap();
+        assert_eq!(vec!["rule Newline is not SCREAMING_CASE, it cannot be renamed: NEWLINE is a built-in rule"], diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>());
+
+        // Both rules cannot be renamed to `key_value`, the second one is reported after the fix.
+        let source = "file = { KeyValue ~ keyValue }\nKeyValue = { \"a\" }\nkeyValue = { \"b\" }\n";
+        let (fixed, renames) = Linter::with_config(source, config.clone()).fix_renames().unwrap();
+        assert_eq!(vec![("KeyValue".to_string(), "key_value".to_string())], renames);
+        let diagnostics = Linter::with_config(&fixed, config).lint().unwrap();
+        assert_eq!(vec!["rule keyValue is not snake_case, it cannot be renamed: rule key_value is already defined"], diagnostics.iter().map(|d| d.message.as_str()).collect::<Vec<_>>());
+
+        assert_eq!("http_request", snake_case("HTTPRequest"));
+        assert_eq!("value2_x", snake_case("value2X"));
+        assert_eq!("a_b", snake_case("a_b"));
+    }
+
+    #[test]
+    fn test_tags() {
+        let source = indoc::indoc! {r#"
+            pair = { #key = key ~ "=" ~ #value = value ~ ("," ~ #value = value)? }
+            op = { #op = add | #op = sub }
+            literal = { #sign = "-"? ~ #digits = digits ~ #ws = ws ~ #not = !"x" ~ #ok = ws_pair }
+            atomic = @{ #inner = key }
+            key = { ASCII_ALPHA+ }
+            value = { ASCII_DIGIT+ }
+            add = { "+" }
+            sub = { "-" }
+            digits = { ASCII_DIGIT+ }
+            ws = _{ " " }
+            ws_pair = _{ ws ~ key }
+            camel = { #someValue = key ~ #SCREAMING = key }
+        "#};
+        let diagnostics = Linter::new(source).lint().unwrap();
+        let diagnostics: Vec<(Lint, &str, usize, usize)> = diagnostics.iter().map(|d| (d.lint, d.message.as_str(), d.line, d.col)).collect();
+        assert_eq!(vec![(Lint::DuplicateTag, "tag #value is used more than once in rule pair", 1, 53), (Lint::IneffectiveTag, r#"tag #sign has no effect, "-"? produces no pair"#, 3, 13), (Lint::IneffectiveTag, "tag #ws has no effect, ws produces no pair", 3, 47), (Lint::IneffectiveTag, r#"tag #not has no effect, !"x" produces no pair"#, 3, 58), (Lint::IneffectiveTag, "tag #inner has no effect in atomic rule atomic", 4, 13), (Lint::InconsistentTagNaming, "tag #someValue is camelCase, the other tags are snake_case", 12, 11), (Lint::InconsistentTagNaming, "tag #SCREAMING is SCREAMING_CASE, the other tags are snake_case", 12, 30),], diagnostics);
+
+        let diagnostics = Linter::new("a = { #a = b ~ #a = b }\nb = { \"b\" }").lint().unwrap();
+        assert_eq!(vec![Note { line: 1, col: 7, message: "#a is first used here".to_string() }], diagnostics[0].notes);
+
+        assert_eq!(("snake_case", "SCREAMING_CASE", "PascalCase", "camelCase", "snake_case"), (tag_case("a_b"), tag_case("A_B"), tag_case("AB_c"), tag_case("aB"), tag_case("_a1")));
+    }
+
+    #[test]
+    fn test_stack() {
+        let source = indoc::indoc! {r#"
+            file = { SOI ~ (string | list)* ~ EOI }
+            string = { PUSH("'" | "\"") ~ (!PEEK ~ ANY)* ~ POP }
+            list = { (PUSH("[") ~ item)* ~ close }
+            close = { "]" ~ DROP }
+            item = { "x" }
+        "#};
+        let diagnostics = Linter::new(source).lint().unwrap();
+        let diagnostics: Vec<(Lint, Level, usize, usize)> = diagnostics.iter().map(|d| (d.lint, d.level, d.line, d.col)).collect();
+        // `list` drops a value which is not pushed without items, the repetition of `file` is not reported again.
+        assert_eq!(vec![(Lint::StackUnderflow, Level::Warn, 1, 26), (Lint::UnbalancedPush, Level::Warn, 3, 10)], diagnostics);
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("a", "a"));
+        assert!(!wildcard_match("a", "ab"));
+        assert!(wildcard_match("*_op", "add_op"));
+        assert!(!wildcard_match("*_op", "add_ops"));
+        assert!(wildcard_match("a*b*c", "aXbYbc"));
+        assert!(wildcard_match("*", ""));
+    }
+
+    #[test]
+    fn test_lint_config() {
+        let table: Value = r#"
+        [lints]
+        unused_rule = "allow"
+        left_recursion = "warn"
+        "#
+        .parse()
+        .unwrap();
+        let config = LintConfig::from_toml(&table).unwrap();
+        assert_eq!(Level::Allow, config.level(Lint::UnusedRule));
+        assert_eq!(Level::Warn, config.level(Lint::LeftRecursion));
+        assert_eq!(Level::Deny, config.level(Lint::UndefinedRule));
+
+        let diagnostics = Linter::with_config("a = { a ~ \"a\" }\nb = _{ \"b\" }\n", config).lint().unwrap();
+        assert_eq!(vec![(Lint::LeftRecursion, Level::Warn)], diagnostics.iter().map(|d| (d.lint, d.level)).collect::<Vec<_>>());
+
+        let table: Value = "entry_rules = [\"file\", \"expr\"]\n".parse().unwrap();
+        assert_eq!(vec!["file", "expr"], LintConfig::from_toml(&table).unwrap().entry_rules);
+        let table: Value = "missing_docs_exempt = \"*_op\"\n".parse().unwrap();
+        assert_eq!(vec!["*_op"], LintConfig::from_toml(&table).unwrap().missing_docs_exempt);
+        let table: Value = "screaming_rules = [\"NEW*\"]\nsilent_prefix = \"_\"\n".parse().unwrap();
+        let config = LintConfig::from_toml(&table).unwrap();
+        assert_eq!((vec!["NEW*".to_string()], Some("_".to_string())), (config.screaming_rules, config.silent_prefix));
+        let table: Value = "silent_prefix = 1\n".parse().unwrap();
+        assert!(LintConfig::from_toml(&table).is_err());
+        let table: Value = "entry_rules = 1\n".parse().unwrap();
+        assert!(LintConfig::from_toml(&table).is_err());
+
+        let table: Value = "[lints]\nno_such_lint = \"allow\"\n".parse().unwrap();
+        assert!(LintConfig::from_toml(&table).is_err());
+        let table: Value = "[lints]\nunused_rule = \"ignore\"\n".parse().unwrap();
+        assert!(LintConfig::from_toml(&table).is_err());
+    }
+}
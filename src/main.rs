@@ -1,37 +1,171 @@
-use ignore::{overrides::OverrideBuilder, WalkBuilder};
-use pest_fmt::{Formatter, PestResult};
-use std::{error::Error, fs, io::Read, path::Path};
+use cache::Cache;
+use report::{FileReport, MessageFormat, Status};
+use ignore::{overrides::OverrideBuilder, WalkBuilder, WalkState};
+use pest_fmt::{Config, Formatter, LintConfig, PestError, PestResult};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 use toml::Value;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+
+mod cache;
+mod commands;
+mod git;
+mod report;
+mod watch;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// The file or path to format
     #[arg(default_value = ".")]
     file: Vec<String>,
     #[clap(long, short, default_value = "false")]
     stdin: bool,
+    /// Number of threads to format directories with, defaults to the number of CPUs
+    #[clap(long, short, default_value = "0")]
+    jobs: usize,
+    /// Skip the files known to be formatted, which are recorded in `target/pestfmt-cache` of the project
+    #[clap(long, default_value = "false")]
+    cache: bool,
+    /// The directory of the cache of formatted files, implies `--cache`
+    #[clap(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Only format the files changed from `HEAD`, staged or untracked in git
+    #[clap(long, default_value = "false")]
+    changed: bool,
+    /// Only format the files changed from the git revision, staged or untracked
+    #[clap(long, value_name = "REV")]
+    since: Option<String>,
+    /// Watch the files and directories, and format the `.pest` files when they change
+    #[clap(long, short, default_value = "false")]
+    watch: bool,
+    /// The format of the messages
+    #[clap(long, value_enum, default_value = "human")]
+    message_format: MessageFormat,
+    /// Include the line edits of the formatted files in the JSON messages
+    #[clap(long, default_value = "false")]
+    edits: bool,
+    /// Keep a copy of the formatted files with the `.bak` extension
+    #[clap(long, default_value = "false")]
+    backup: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check the grammars for mistakes, the levels of the lints are read from `[pest.lints]`
+    Lint {
+        /// The file or path to check
+        #[arg(default_value = ".")]
+        file: Vec<String>,
+        /// Fix the problems that can be fixed, e.g.: remove the unused rules, then format the files
+        #[clap(long, default_value = "false")]
+        fix: bool,
+        /// Use the rules in the `Rule::name` paths of the Rust files in the path as entry rules, with `--fix`
+        /// the paths of the rules renamed for `naming_convention` are renamed too
+        #[clap(long, value_name = "PATH")]
+        rust: Vec<PathBuf>,
+        /// With `--fix`, rename the rules for `naming_convention` even without `--rust`, the `Rule::name` paths
+        /// of the Rust code must then be renamed by hand
+        #[clap(long, default_value = "false")]
+        rename_rules: bool,
+    },
+    /// Rename a rule in the `.pest` files and in the inline grammars of the Rust files
+    Rename {
+        /// The rule to rename
+        old: String,
+        /// The new name of the rule
+        new: String,
+        /// The file or path to rename in
+        #[arg(default_value = ".")]
+        file: Vec<String>,
+        /// Also rename the `Rule::old` paths in the Rust files, the ones of other parsers are kept and the ones not
+        /// resolved to a parser are reported
+        #[clap(long, default_value = "false")]
+        rust_paths: bool,
+    },
+    /// Print the graph of the references between the rules of a grammar
+    Graph {
+        /// The grammar file
+        file: PathBuf,
+        /// The format of the graph
+        #[clap(long, value_enum, default_value = "dot")]
+        format: commands::GraphFormat,
+        /// Only print the rules reachable from the rule
+        #[clap(long)]
+        rule: Option<String>,
+    },
+    /// Print the statistics of the grammars: rules by modifier, tags, stack operations, depth and docs
+    Stats {
+        /// The file or path of the grammars
+        #[arg(default_value = ".")]
+        file: Vec<String>,
+        /// The format of the statistics
+        #[clap(long, value_enum, default_value = "text")]
+        format: commands::StatsFormat,
+    },
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
-
-    if cli.stdin {
+    let rustfmt = fs::read_to_string("rustfmt.toml").unwrap_or_default();
+    let config = read_config(&rustfmt)?;
+
+    if let Some(command) = cli.command {
+        let success = match command {
+            Command::Lint { file, fix, rust, rename_rules } => {
+                let lint_config = read_lint_config(&rustfmt)?;
+                let rust_rules = commands::rust_rule_references(&rust)?;
+                let fix = fix.then_some(commands::Fix { format: &config, rust: &rust, rename_rules });
+                commands::lint(&collect_files(&file), &lint_config, &rust_rules, fix.as_ref())
+            }
+            Command::Rename { old, new, file, rust_paths } => commands::rename(&collect_files(&file), &file, &old, &new, rust_paths)?,
+            Command::Graph { file, format, rule } => commands::graph(&file, format, rule.as_deref()),
+            Command::Stats { file, format } => commands::stats(&collect_files(&file), format),
+        };
+        if !success {
+            std::process::exit(1);
+        }
+    } else if cli.stdin {
         let mut source = String::new();
         std::io::stdin().read_to_string(&mut source).expect("failed read source from stdin");
         println!("{}", source);
     } else {
-        process_files(cli.file)?;
+        let cache = match cli.cache_dir {
+            Some(dir) => Some(Cache::new(dir, &config)?),
+            None if cli.cache => Some(Cache::new(cache::default_dir()?, &config)?),
+            None => None,
+        };
+        let cache = cache.as_ref();
+        if cli.watch {
+            return watch::watch(cli.file, &config, cache, cli.backup);
+        }
+
+        let changed = match (&cli.since, cli.changed) {
+            (Some(rev), _) => Some(Arc::new(git::changed_files(".", rev)?)),
+            (None, true) => Some(Arc::new(git::changed_files(".", "HEAD")?)),
+            (None, false) => None,
+        };
+        let reports = process_files(cli.file, &config, cli.jobs, cache, changed, cli.backup);
+        report::print_reports(&reports, cli.message_format, cli.edits);
     }
 
     Ok(())
 }
 
-fn process_files(paths: Vec<String>) -> Result<(), Box<dyn Error>> {
+/// Format the files and directories, `changed` restricts to the canonical paths of the changed files.
+/// Returns the reports of the files.
+fn process_files(paths: Vec<String>, config: &Config, jobs: usize, cache: Option<&Cache>, changed: Option<Arc<HashSet<PathBuf>>>, backup: bool) -> Vec<FileReport> {
     let mut paths = paths;
-    let mut updated = 0;
+    let mut reports = vec![];
 
     // If there not argument, format the current directory
     if paths.is_empty() {
@@ -39,65 +173,124 @@ fn process_files(paths: Vec<String>) -> Result<(), Box<dyn Error>> {
     }
 
     for path in paths {
-        let path = Path::new(&path);
+        let path = PathBuf::from(path);
         if path.exists() {
             if path.is_file() {
-                if let Ok(changed) = format_file(&path, &path) {
-                    if changed {
-                        updated += 1
-                    }
+                if !is_changed(&path, changed.as_deref()) {
+                    reports.push(FileReport { path, status: Status::Skipped });
+                    continue;
                 }
+                let result = format_file(&path, &path, config, cache, backup);
+                reports.push(FileReport::new(path, result));
             } else {
-                let walker = build_walker(path);
-                updated += format_directory(walker)?;
+                let walker = build_walker(&path, changed.clone());
+                reports.extend(format_directory(&path, walker, config, jobs, cache, backup));
             }
         } else {
-            eprintln!("no such file or directory: {}", path.display());
+            reports.push(FileReport::new(path, Err(PestError::IOError("no such file or directory".to_string()))));
         }
     }
 
-    println!("Formatted {} files", updated);
-    Ok(())
+    reports
 }
 
-fn format(source: &str) -> PestResult<String> {
-    let fmt = Formatter::new(source);
+fn format(source: &str, config: &Config) -> PestResult<String> {
+    let fmt = Formatter::with_config(source, config.clone());
     fmt.format()
 }
 
-fn format_file<P: AsRef<Path>>(path_from: P, path_to: P) -> PestResult<bool> {
+/// Format the file, returns the `(input, output)`.
+/// The file is only written if changed, with a `.bak` copy of the input if `backup` is set.
+fn format_file<P: AsRef<Path>>(path_from: P, path_to: P, config: &Config, cache: Option<&Cache>, backup: bool) -> PestResult<(String, String)> {
     let input = std::fs::read_to_string(path_from)?;
-    let output = format(&input)?;
-
-    let mut file = std::fs::File::create(path_to)?;
-    std::io::Write::write_all(&mut file, output.as_bytes())?;
-    Ok(input != output)
-}
-
-/// Format all files in the given directory.
-/// Returns the number of files that were formatted.
-fn format_directory(walker: WalkBuilder) -> Result<usize, Box<dyn Error>> {
-    let mut updated = 0;
-    for entry in walker.build() {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(path) = path.to_str() {
-                if path.ends_with(".pest") {
-                    if let Ok(changed) = format_file(path, path) {
-                        if changed {
-                            updated += 1;
-                        }
+    if cache.is_some_and(|cache| cache.contains(&input)) {
+        return Ok((input.clone(), input));
+    }
+    let output = format(&input, config)?;
+
+    if input != output {
+        write_file(path_to.as_ref(), &output, backup)?;
+    }
+    if let Some(cache) = cache {
+        cache.insert(&output);
+    }
+    Ok((input, output))
+}
+
+/// Write the file through a temporary file in the same directory and a rename, so the file is never left
+/// half written. The permissions of the existing file are kept.
+fn write_file(path: &Path, content: &str, backup: bool) -> io::Result<()> {
+    // Write to the target of a symlink instead of replacing the symlink.
+    let path = path.canonicalize()?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.pestfmt.tmp", name, std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        fs::set_permissions(&temp, fs::metadata(&path)?.permissions())?;
+
+        if backup {
+            fs::copy(&path, path.with_file_name(format!("{}.bak", name)))?;
+        }
+        fs::rename(&temp, &path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Format all files in the `root` directory with `jobs` threads, `0` to use the number of CPUs.
+/// Returns the reports of the files sorted by path.
+fn format_directory(root: &Path, mut walker: WalkBuilder, config: &Config, jobs: usize, cache: Option<&Cache>, backup: bool) -> Vec<FileReport> {
+    let reports = Mutex::new(vec![]);
+
+    walker.threads(jobs).build_parallel().run(|| {
+        let reports = &reports;
+        Box::new(move |entry| {
+            let report = match entry {
+                Ok(entry) => {
+                    let path = entry.path();
+                    if !path.is_file() || !path.to_string_lossy().ends_with(".pest") {
+                        return WalkState::Continue;
                     }
+                    FileReport::new(path.to_path_buf(), format_file(path, path, config, cache, backup))
                 }
-            }
+                Err(e) => {
+                    let (path, message) = walk_error(&e, root);
+                    FileReport::new(path, Err(PestError::IOError(message)))
+                }
+            };
+
+            reports.lock().unwrap().push(report);
+            WalkState::Continue
+        })
+    });
+
+    let mut reports = reports.into_inner().unwrap();
+    reports.sort_by(|a, b| a.path.cmp(&b.path));
+    reports
+}
+
+/// The path and the message of the error of the walker, the errors without a path are reported at the `root`.
+fn walk_error(error: &ignore::Error, root: &Path) -> (PathBuf, String) {
+    match error {
+        ignore::Error::WithPath { path, err } => (path.clone(), walk_error(err, root).1),
+        ignore::Error::WithLineNumber { line, err } => {
+            let (path, message) = walk_error(err, root);
+            (path, format!("line {}: {}", line, message))
         }
+        ignore::Error::WithDepth { err, .. } => walk_error(err, root),
+        ignore::Error::Loop { child, .. } => (child.clone(), error.to_string()),
+        ignore::Error::Partial(errors) if errors.len() == 1 => walk_error(&errors[0], root),
+        _ => (root.to_path_buf(), error.to_string()),
     }
-
-    Ok(updated)
 }
 
-fn build_walker<P: AsRef<Path> + Copy>(root: P) -> WalkBuilder {
+fn build_walker<P: AsRef<Path> + Copy>(root: P, changed: Option<Arc<HashSet<PathBuf>>>) -> WalkBuilder {
     let mut builder = ignore::WalkBuilder::new(root);
     builder.follow_links(true).git_ignore(true);
 
@@ -123,7 +316,7 @@ fn build_walker<P: AsRef<Path> + Copy>(root: P) -> WalkBuilder {
 
         if let Some(path) = entry.path().to_str() {
             if path.ends_with(".pest") {
-                return true;
+                return is_changed(entry.path(), changed.as_deref());
             }
         }
 
@@ -133,6 +326,14 @@ fn build_walker<P: AsRef<Path> + Copy>(root: P) -> WalkBuilder {
     builder
 }
 
+/// Whether the file is in the changed files, always true without `--changed` or `--since`.
+fn is_changed(path: &Path, changed: Option<&HashSet<PathBuf>>) -> bool {
+    match changed {
+        Some(changed) => path.canonicalize().is_ok_and(|path| changed.contains(&path)),
+        None => true,
+    }
+}
+
 fn read_rustfmt(input: &str) -> Vec<String> {
     if let Ok(rust_fmt) = input.parse::<Value>() {
         if let Some(pest) = rust_fmt.get("pest") {
@@ -145,6 +346,44 @@ fn read_rustfmt(input: &str) -> Vec<String> {
     vec![]
 }
 
+/// Read the formatting options from the `[pest]` table.
+fn read_config(input: &str) -> PestResult<Config> {
+    if let Ok(rust_fmt) = input.parse::<Value>() {
+        if let Some(pest) = rust_fmt.get("pest") {
+            return Config::from_toml(pest);
+        }
+    }
+
+    Ok(Config::default())
+}
+
+/// Read the lint options from the `[pest]` table.
+fn read_lint_config(input: &str) -> PestResult<LintConfig> {
+    if let Ok(rust_fmt) = input.parse::<Value>() {
+        if let Some(pest) = rust_fmt.get("pest") {
+            return LintConfig::from_toml(pest);
+        }
+    }
+
+    Ok(LintConfig::default())
+}
+
+/// The files and the `.pest` files in the directories, sorted by path.
+fn collect_files(paths: &[String]) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for path in paths {
+        let path = Path::new(path);
+        if path.is_dir() {
+            files.extend(build_walker(path, None).build().filter_map(Result::ok).map(|entry| entry.into_path()).filter(|path| path.is_file()));
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    files.sort();
+    files
+}
+
 fn toml_string_or_string_list(value: &Value) -> Vec<String> {
     let mut out = vec![];
     match value {
@@ -182,9 +421,193 @@ mod tests {
         assert_eq!(vec!["src/**/*".to_string()], excludes);
     }
 
+    #[test]
+    fn test_read_config() {
+        let text = fs::read_to_string("rustfmt.toml").unwrap();
+        assert_eq!(Config::default(), read_config(&text).unwrap());
+
+        let text = r#"
+        [pest]
+        choice_style = "trailing"
+        "#;
+        assert_eq!(pest_fmt::ChoiceStyle::Trailing, read_config(text).unwrap().choice_style);
+    }
+
+    #[test]
+    fn test_read_lint_config() {
+        let text = fs::read_to_string("rustfmt.toml").unwrap();
+        assert_eq!(LintConfig::default(), read_lint_config(&text).unwrap());
+
+        let text = r#"
+        [pest.lints]
+        unused_rule = "deny"
+        "#;
+        assert_eq!(pest_fmt::Level::Deny, read_lint_config(text).unwrap().level(pest_fmt::Lint::UnusedRule));
+    }
+
+    #[test]
+    fn test_format_directory() {
+        let root = std::env::temp_dir().join(format!("pestfmt-test-format-directory-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("b.pest"), "b={\"b\"}\n").unwrap();
+        fs::write(root.join("a.pest"), "a = { \"a\" }\n").unwrap();
+        fs::write(root.join("sub/c.pest"), "c={\"c\"}\n").unwrap();
+        fs::write(root.join("sub/d.pest"), "d={\n").unwrap();
+        fs::write(root.join("sub/e.txt"), "e={\"e\"}\n").unwrap();
+
+        let reports = format_directory(&root, build_walker(&root, None), &Config::default(), 4, None, false);
+        let paths: Vec<PathBuf> = reports.iter().map(|report| report.path.strip_prefix(&root).unwrap().to_path_buf()).collect();
+        assert_eq!(vec![PathBuf::from("a.pest"), PathBuf::from("b.pest"), PathBuf::from("sub/c.pest"), PathBuf::from("sub/d.pest")], paths);
+        assert!(matches!(reports[0].status, Status::Unchanged));
+        assert!(matches!(reports[1].status, Status::Formatted { .. }));
+        assert!(matches!(reports[2].status, Status::Formatted { .. }));
+        assert!(matches!(reports[3].status, Status::Error(_)));
+        assert_eq!("b = { \"b\" }\n", fs::read_to_string(root.join("b.pest")).unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_walk_error() {
+        let io = || Box::new(ignore::Error::Io(io::Error::new(io::ErrorKind::PermissionDenied, "permission denied")));
+        let error = ignore::Error::WithDepth { depth: 1, err: Box::new(ignore::Error::WithPath { path: PathBuf::from("a/b"), err: io() }) };
+        assert_eq!((PathBuf::from("a/b"), "permission denied".to_string()), walk_error(&error, Path::new("a")));
+        assert_eq!((PathBuf::from("a"), "permission denied".to_string()), walk_error(&io(), Path::new("a")));
+
+        #[cfg(unix)]
+        {
+            let root = std::env::temp_dir().join(format!("pestfmt-test-walk-error-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("sub")).unwrap();
+            std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+
+            let reports = format_directory(&root, build_walker(&root, None), &Config::default(), 1, None, false);
+            assert_eq!(vec![root.join("sub/loop")], reports.iter().map(|report| report.path.clone()).collect::<Vec<_>>());
+            assert!(matches!(&reports[0].status, Status::Error(PestError::IOError(message)) if message.contains("loop")));
+
+            fs::remove_dir_all(&root).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_format_file() {
+        let root = std::env::temp_dir().join(format!("pestfmt-test-format-file-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join("a.pest");
+
+        // Unchanged files are not written.
+        fs::write(&path, "a = { \"a\" }\n").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        format_file(&path, &path, &Config::default(), None, true).unwrap();
+        assert_eq!(modified, fs::metadata(&path).unwrap().modified().unwrap());
+        assert!(!root.join("a.pest.bak").exists());
+
+        fs::write(&path, "a={\"a\"}\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        format_file(&path, &path, &Config::default(), None, true).unwrap();
+        assert_eq!("a = { \"a\" }\n", fs::read_to_string(&path).unwrap());
+        assert_eq!("a={\"a\"}\n", fs::read_to_string(root.join("a.pest.bak")).unwrap());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(0o640, fs::metadata(&path).unwrap().permissions().mode() & 0o777);
+        }
+        // No temporary file is left.
+        assert_eq!(2, fs::read_dir(&root).unwrap().count());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rename() {
+        let root = std::env::temp_dir().join(format!("pestfmt-test-rename-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(root.join("src/grammar.pest"), "a = { \"a\" }\n").unwrap();
+        let lib = "#[derive(Parser)]\n#[grammar = \"grammar.pest\"]\nstruct A;\n\nfn f() -> Rule {\n    Rule::a\n}\n";
+        fs::write(root.join("src/lib.rs"), lib).unwrap();
+        let rename = || commands::rename(&collect_files(&[root.display().to_string()]), &[root.display().to_string()], "a", "b", true).unwrap();
+
+        assert!(rename());
+        assert_eq!("b = { \"a\" }\n", fs::read_to_string(root.join("src/grammar.pest")).unwrap());
+        assert_eq!(lib.replace("Rule::a", "Rule::b"), fs::read_to_string(root.join("src/lib.rs")).unwrap());
+
+        // Only the paths resolved to the renamed grammar, when another parser may own the unresolved ones
+        fs::write(root.join("src/grammar.pest"), "a = { \"a\" }\n").unwrap();
+        fs::write(root.join("src/lib.rs"), lib).unwrap();
+        let other = "mod other {\n    #[derive(Parser)]\n    #[grammar_inline = \"c = { \\\"c\\\" }\"]\n    struct C;\n\n    const _: Rule = Rule::a;\n}\n\nconst _: crate::Rule = crate::Rule::a;\n";
+        fs::write(root.join("src/other.rs"), other).unwrap();
+        assert!(rename());
+        assert_eq!(lib.replace("Rule::a", "Rule::b"), fs::read_to_string(root.join("src/lib.rs")).unwrap());
+        assert_eq!(other, fs::read_to_string(root.join("src/other.rs")).unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rust_rule_references() {
+        let root = std::env::temp_dir().join(format!("pestfmt-test-rust-rules-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(root.join("src/a.pest"), "a = { \"a\" }\n").unwrap();
+        fs::write(root.join("src/b.pest"), "b = { \"b\" }\n").unwrap();
+        let lib = "mod a {\n    #[derive(Parser)]\n    #[grammar = \"a.pest\"]\n    struct A;\n\n    const _: Rule = Rule::a;\n}\n\nmod b {\n    #[derive(Parser)]\n    #[grammar = \"b.pest\"]\n    struct B;\n\n    // Rule::c\n    const _: Rule = Rule::b;\n}\n\nconst _: x::Rule = x::Rule::d;\n";
+        fs::write(root.join("src/lib.rs"), lib).unwrap();
+
+        let rules = commands::rust_rule_references(&[root.join("src")]).unwrap();
+        assert_eq!(vec!["a", "d"], rules.of(&root.join("src/a.pest")));
+        assert_eq!(vec!["b", "d"], rules.of(&root.join("src/b.pest")));
+        assert_eq!(vec!["d"], rules.of(&root.join("src/other.pest")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_lint_fix_renames() {
+        let root = std::env::temp_dir().join(format!("pestfmt-test-lint-fix-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+        let grammar = "file = { keyValue }\nkeyValue = { \"a\" }\n";
+        let renamed = "file      = { key_value }\nkey_value = { \"a\" }\n";
+        let lib = "#[derive(Parser)]\n#[grammar = \"grammar.pest\"]\nstruct A;\n\nconst _: Rule = Rule::keyValue;\n";
+        let reset = || {
+            fs::write(root.join("src/grammar.pest"), grammar).unwrap();
+            fs::write(root.join("src/lib.rs"), lib).unwrap();
+        };
+        let config = LintConfig { levels: std::collections::HashMap::from([(pest_fmt::Lint::NamingConvention, pest_fmt::Level::Warn)]), ..Default::default() };
+        let rust = [root.join("src")];
+        let lint = |rust: &[PathBuf], rename_rules| commands::lint(&[root.join("src/grammar.pest")], &config, &Default::default(), Some(&commands::Fix { format: &Config::default(), rust, rename_rules }));
+
+        // Opt-in without the Rust files
+        reset();
+        assert!(lint(&[], false));
+        assert_eq!(grammar, fs::read_to_string(root.join("src/grammar.pest")).unwrap());
+        assert!(lint(&[], true));
+        assert_eq!(renamed, fs::read_to_string(root.join("src/grammar.pest")).unwrap());
+        assert_eq!(lib, fs::read_to_string(root.join("src/lib.rs")).unwrap());
+
+        // The `Rule::name` paths are renamed with the grammar
+        reset();
+        assert!(lint(&rust, false));
+        assert_eq!(renamed, fs::read_to_string(root.join("src/grammar.pest")).unwrap());
+        assert_eq!(lib.replace("keyValue", "key_value"), fs::read_to_string(root.join("src/lib.rs")).unwrap());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
     #[test]
     fn test_build_walker() {
-        let walker = build_walker(".");
+        let walker = build_walker(".", None);
 
         let mut files: Vec<String> = vec![];
         for entry in walker.build() {
@@ -197,5 +620,9 @@ mod tests {
 
         #[cfg(not(target_os = "windows"))]
         assert_eq!(vec!["./src/grammar.pest".to_string()], files);
+
+        let changed: HashSet<PathBuf> = vec![Path::new("src/lib.rs").canonicalize().unwrap()].into_iter().collect();
+        let walker = build_walker(".", Some(Arc::new(changed)));
+        assert!(walker.build().filter_map(Result::ok).all(|entry| entry.path().is_dir()));
     }
 }
@@ -1,8 +1,8 @@
-use crate::{Formatter, Node};
+use crate::{DocSpacing, Formatter, NewlineStyle, Node, RuleSpacing};
 
 impl Formatter<'_> {
     /// If match the text containes "\n" between current pair and next pair, then push a new line
-    /// For example:
+    /// For example (with the default `blank_lines_upper_bound = 1`):
     ///
     /// 1. `a = { "a" }\nb = { "b" }` => `a = { "a" }\nb = { "b" }`
     /// 2. `a = { "a" }\n\nb = { "b" }` => `a = { "a" }\n\nb = { "b" }`
@@ -15,15 +15,207 @@ impl Formatter<'_> {
         }
 
         let part = self.get_str((span.0, span.1));
-        // If there have at least 2 "\n" then push a new line
-        if part.matches('\n').count() >= 2 {
+        let mut blank_lines = part.matches('\n').count().saturating_sub(1).min(self.config.blank_lines_upper_bound);
+        // The trailing comment of a rule is on the same line.
+        if let (Some(Node::Rule(_)), true) = (nodes.last(), part.contains('\n')) {
+            blank_lines = blank_lines.max(self.config.blank_lines_lower_bound);
+        }
+
+        for _ in 0..blank_lines {
             nodes.push(Node::Str("".to_string()));
         }
     }
+
+    /// Insert or remove the blank lines by the `blank_lines_between_rules` and
+    /// `blank_line_after_grammar_doc` options.
+    pub(super) fn separate_nodes(&self, nodes: &mut Vec<Node>) {
+        if self.config.blank_lines_between_rules != RuleSpacing::Preserve {
+            let mut i = 0;
+            while i < nodes.len() {
+                if let Node::Rule(rule) = &nodes[i] {
+                    let next = nodes[i + 1..].iter().position(|node| matches!(node, Node::Rule(_))).map(|j| i + 1 + j);
+                    if let Some(Node::Rule(next_rule)) = next.map(|j| &nodes[j]) {
+                        let separate = match self.config.blank_lines_between_rules {
+                            RuleSpacing::MultiLine => rule.code.contains('\n') || next_rule.code.contains('\n'),
+                            _ => true,
+                        };
+
+                        // The blank line is put before the doc comments of the next rule.
+                        if separate && !nodes[i + 1..next.unwrap()].iter().any(Node::is_blank_line) {
+                            nodes.insert(i + 1, Node::Str("".to_string()));
+                        }
+                    }
+                }
+                i += 1;
+            }
+        }
+
+        if self.config.blank_line_after_grammar_doc != DocSpacing::Preserve {
+            let last_doc = nodes.iter().rposition(|node| matches!(node, Node::LineDoc(doc) if doc.starts_with("//!")));
+            if let Some(i) = last_doc {
+                while nodes.get(i + 1).is_some_and(Node::is_blank_line) {
+                    nodes.remove(i + 1);
+                }
+                if self.config.blank_line_after_grammar_doc == DocSpacing::Always {
+                    nodes.insert(i + 1, Node::Str("".to_string()));
+                }
+            }
+        }
+    }
+}
+
+impl Formatter<'_> {
+    /// The line ending of the output, by the `newline_style` option.
+    pub(super) fn newline(&self) -> &'static str {
+        let windows = match self.config.newline_style {
+            NewlineStyle::Auto => self.input.find('\n').is_some_and(|i| self.input[..i].ends_with('\r')),
+            NewlineStyle::Unix => false,
+            NewlineStyle::Windows => true,
+            NewlineStyle::Native => cfg!(windows),
+        };
+
+        if windows { "\r\n" } else { "\n" }
+    }
+
+    /// Convert the `\n` of the code to the line endings of the `newline_style` option.
+    pub(super) fn apply_newline_style(&self, code: &str) -> String {
+        // The `\r` may be left in the multi-line strings, block comments.
+        code.replace("\r\n", "\n").replace('\n', self.newline())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::{Config, DocSpacing, NewlineStyle, RuleSpacing};
+
+    #[test]
+    fn test_blank_lines_bounds() {
+        expect_correction! {
+            Config { blank_lines_upper_bound: 2, blank_lines_lower_bound: 1, ..Default::default() } =>
+            r#"
+            a = { "a" }
+            b = { "b" }
+
+
+
+            // This is comment
+            c = { "c" }
+            "#,
+            r#"
+            a = { "a" }
+
+            b = { "b" }
+
+
+            // This is comment
+            c = { "c" }
+            "#,
+        }
+
+        expect_correction! {
+            Config { blank_lines_upper_bound: 0, ..Default::default() } =>
+            r#"
+            a = { "a" }
+
+            b = { "b" }
+            "#,
+            r#"
+            a = { "a" }
+            b = { "b" }
+            "#,
+        }
+    }
+
+    #[test]
+    fn test_blank_lines_between_rules() {
+        expect_correction! {
+            Config { blank_lines_between_rules: RuleSpacing::Always, ..Default::default() } =>
+            r#"
+            a = { "a" }
+            bb = { "b" }
+            /// Doc
+            multi = {
+                "a"
+              | "b"
+            }
+            c = { "c" }
+            "#,
+            r#"
+            a = { "a" }
+
+            bb = { "b" }
+
+            /// Doc
+            multi = {
+                "a"
+              | "b"
+            }
+
+            c = { "c" }
+            "#,
+        }
+
+        expect_correction! {
+            Config { blank_lines_between_rules: RuleSpacing::MultiLine, ..Default::default() } =>
+            r#"
+            a = { "a" }
+            bb = { "b" }
+            /// Doc
+            multi = {
+                "a"
+              | "b"
+            }
+            c = { "c" }
+            d = { "d" }
+            "#,
+            r#"
+            a  = { "a" }
+            bb = { "b" }
+
+            /// Doc
+            multi = {
+                "a"
+              | "b"
+            }
+
+            c = { "c" }
+            d = { "d" }
+            "#,
+        }
+    }
+
+    #[test]
+    fn test_blank_line_after_grammar_doc() {
+        expect_correction! {
+            Config { blank_line_after_grammar_doc: DocSpacing::Always, ..Default::default() } =>
+            r#"
+            //! Grammar doc
+            a = { "a" }
+            "#,
+            r#"
+            //! Grammar doc
+
+            a = { "a" }
+            "#,
+        }
+
+        expect_correction! {
+            Config { blank_line_after_grammar_doc: DocSpacing::Never, ..Default::default() } =>
+            r#"
+            //! Grammar doc
+
+
+            // comment
+            a = { "a" }
+            "#,
+            r#"
+            //! Grammar doc
+            // comment
+            a = { "a" }
+            "#,
+        }
+    }
+
     #[test]
     fn test_keep_exist_newline() {
         expect_correction! {
@@ -50,4 +242,27 @@ mod tests {
             "#,
         }
     }
+
+    #[test]
+    fn test_newline_style() {
+        let format = |source: &str, newline_style| {
+            let config = Config { newline_style, ..Default::default() };
+            crate::Formatter::with_config(source, config).format().unwrap()
+        };
+
+        let source = "a={\"a\"}\r\n/*\r\ncomment1\r\ncomment2\r\n*/\r\nb={\"b\"}\n";
+        let windows = "a = { \"a\" }\r\n/*\r\n    comment1\r\n    comment2\r\n*/\r\nb = { \"b\" }\r\n";
+        let unix = "a = { \"a\" }\n/*\n    comment1\n    comment2\n*/\nb = { \"b\" }\n";
+
+        assert_eq!(windows, format(source, NewlineStyle::Auto));
+        assert_eq!(unix, format(source, NewlineStyle::Unix));
+        assert_eq!(windows, format(unix, NewlineStyle::Windows));
+        assert_eq!(unix, format(unix, NewlineStyle::Auto));
+    }
+
+    #[test]
+    fn test_bom() {
+        let fmt = crate::Formatter::new("\u{feff}a={\"a\"}\r\n");
+        assert_eq!("\u{feff}a = { \"a\" }\r\n", fmt.format().unwrap());
+    }
 }
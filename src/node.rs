@@ -6,6 +6,8 @@ pub(crate) enum Node {
     Comment(String),
     LineDoc(String),
     Str(String),
+    /// The source kept byte for byte, by the `pestfmt::skip` and `pestfmt-off` directives
+    Verbatim(String),
 }
 
 impl Node {
@@ -15,8 +17,14 @@ impl Node {
             Node::Comment(c) => c.to_owned(),
             Node::LineDoc(c) => c.to_owned(),
             Node::Str(c) => c.to_owned(),
+            Node::Verbatim(c) => c.to_owned(),
         }
     }
+
+    /// Whether the node is an empty line, which is pushed for a blank line in the input.
+    pub(crate) fn is_blank_line(&self) -> bool {
+        matches!(self, Node::Str(s) if s.is_empty())
+    }
 }
 
 #[derive(Clone)]
@@ -29,6 +37,8 @@ pub(crate) struct GrammarRule {
     pub code: String,
     /// (start_line, end_line)
     pub lines: (usize, usize),
+    /// The code lines are relative to the `{`, see `ChoiceStyle::Hanging`
+    pub hanging: bool,
 }
 
 impl GrammarRule {
@@ -41,7 +51,24 @@ impl GrammarRule {
         code.push_str(" = ");
 
         code.push_str(&self.modifier);
-        code.push_str(&self.code);
+
+        if self.hanging {
+            // Indent the continuation lines to the column of `{`, except the closing brace.
+            let padding = " ".repeat(code.chars().count());
+            let lines: Vec<&str> = self.code.split('\n').collect();
+            let last = lines.len() - 1;
+            for (i, line) in lines.into_iter().enumerate() {
+                if i > 0 {
+                    code.push('\n');
+                    if i < last {
+                        code.push_str(&padding);
+                    }
+                }
+                code.push_str(line);
+            }
+        } else {
+            code.push_str(&self.code);
+        }
 
         code
     }
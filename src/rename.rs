@@ -0,0 +1,314 @@
+//! Rename a rule in the grammars and in the Rust sources that use them.
+
+use crate::{
+    formatter::{PestParser, Rule},
+    rust::{Parser as RustParser, Source},
+    PestError, PestResult,
+};
+use pest::{Parser, Span};
+use pest_meta::validator;
+
+/// The built-in rules that are not pest keywords, the unicode properties are checked apart.
+const BUILTINS: &[&str] = &["ASCII_DIGIT", "ASCII_NONZERO_DIGIT", "ASCII_BIN_DIGIT", "ASCII_OCT_DIGIT", "ASCII_HEX_DIGIT", "ASCII_ALPHA_LOWER", "ASCII_ALPHA_UPPER", "ASCII_ALPHA", "ASCII_ALPHANUMERIC", "ASCII", "NEWLINE"];
+
+/// Rename the rule `old` to `new` in the grammar, the definition and every reference.
+///
+/// The grammar may only reference `old`, as a part of a grammar split in several files. Fails if `new` is not
+/// an identifier, is a keyword or a built-in rule, or is already defined in the grammar.
+pub fn rename_rule(input: &str, old: &str, new: &str) -> PestResult<String> {
+    let body = input.trim_start_matches('\u{feff}');
+    let bom = input.len() - body.len();
+
+    let spans = rename_spans(&[body], old, new)?.remove(0);
+    Ok(replace(input, spans.into_iter().map(|(start, end)| (start + bom, end + bom)).collect(), new))
+}
+
+/// How the `Rule::old` paths of a Rust source are renamed, see `rename_rust`.
+pub struct RulePaths<'a> {
+    /// Whether the grammar of a `#[grammar = "..."]` attribute is renamed, by the path of the attribute
+    pub renamed_file: &'a dyn Fn(&str) -> bool,
+    /// Whether the unresolved paths are renamed too
+    pub unresolved: bool,
+}
+
+/// The result of `rename_rust`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RustRename {
+    pub output: String,
+    /// The lines of the `Rule::old` paths that are not resolved to a parser of the source, e.g.: a `Rule` imported
+    /// from another file. They are only renamed with `RulePaths::unresolved`.
+    pub unresolved: Vec<usize>,
+    /// Whether the source has a parser whose grammar is not renamed
+    pub other_parsers: bool,
+}
+
+/// Rename the rule `old` to `new` in the `#[grammar_inline = "..."]` grammars of the `#[derive(Parser)]` items
+/// of the Rust source, and in the `Rule::old` paths with `paths`.
+///
+/// The `Rule` of a path is the one of the parsers in the closest module around it, so a path is only renamed if
+/// the grammar of that parser is renamed. The imports are not resolved: the paths without a parser around them,
+/// or with a qualified `Rule`, are unresolved.
+pub fn rename_rust(source: &str, old: &str, new: &str, paths: Option<&RulePaths>) -> PestResult<RustRename> {
+    let rust = Source::new(source);
+    let literals: Vec<_> = rust.parsers.iter().flat_map(|parser| &parser.inline).collect();
+    let grammars: Vec<&str> = literals.iter().map(|literal| literal.value.as_str()).collect();
+
+    let mut ranges = vec![];
+    let mut renamed_literals = vec![];
+    for (literal, spans) in literals.iter().zip(rename_spans(&grammars, old, new)?) {
+        if !spans.is_empty() {
+            renamed_literals.push(literal.range);
+        }
+        for (start, end) in spans {
+            let range = (literal.offsets[start], literal.offsets[end]);
+            if &source[range.0..range.1] != old {
+                return Err(PestError::RenameFail(format!("rule {} is escaped in the inline grammar", old)));
+            }
+            ranges.push(range);
+        }
+    }
+
+    let is_renamed = |parser: &RustParser| parser.inline.iter().any(|literal| renamed_literals.contains(&literal.range)) || paths.is_some_and(|paths| parser.files.iter().any(|file| (paths.renamed_file)(file)));
+    let other_parsers = !rust.parsers.iter().all(is_renamed);
+
+    let mut unresolved = vec![];
+    if let Some(paths) = paths {
+        for (start, end, qualified) in rust.rule_paths(old) {
+            let parsers = if qualified { vec![] } else { rust.parser_at(start) };
+            if parsers.is_empty() {
+                unresolved.push(source[..start].matches('\n').count() + 1);
+                if paths.unresolved {
+                    ranges.push((start, end));
+                }
+            } else if parsers.into_iter().any(is_renamed) {
+                ranges.push((start, end));
+            }
+        }
+    }
+
+    ranges.sort_unstable();
+    Ok(RustRename { output: replace(source, ranges, new), unresolved, other_parsers })
+}
+
+/// The names of the rules defined in the grammar.
+pub fn defined_rules(input: &str) -> PestResult<Vec<String>> {
+    Ok(identifiers(input.trim_start_matches('\u{feff}'))?.into_iter().filter(|(_, definition)| *definition).map(|(span, _)| span.as_str().to_string()).collect())
+}
+
+/// The grammars of the `#[grammar_inline = "..."]` attributes of the `#[derive(Parser)]` items of the Rust
+/// source.
+pub fn inline_grammars(source: &str) -> Vec<String> {
+    Source::new(source).parsers.into_iter().flat_map(|parser| parser.inline).map(|literal| literal.value).collect()
+}
+
+/// Check the names, then returns the spans of the identifiers `old` in each grammar.
+fn rename_spans(grammars: &[&str], old: &str, new: &str) -> PestResult<Vec<Vec<(usize, usize)>>> {
+    check_rename(old, new)?;
+
+    let mut spans = vec![];
+    for grammar in grammars {
+        let identifiers = identifiers(grammar)?;
+        if identifiers.iter().any(|(span, definition)| *definition && span.as_str() == new) {
+            return Err(PestError::RenameFail(format!("rule {} is already defined", new)));
+        }
+        spans.push(identifiers.iter().filter(|(span, _)| span.as_str() == old).map(|(span, _)| (span.start(), span.end())).collect());
+    }
+
+    Ok(spans)
+}
+
+/// Check that `new` is a valid rule name and that neither name is a keyword or a built-in rule.
+pub fn check_rename(old: &str, new: &str) -> PestResult<()> {
+    let is_identifier = PestParser::parse(Rule::identifier, new).map(|pairs| pairs.as_str() == new).unwrap_or(false);
+    if !is_identifier {
+        return Err(PestError::RenameFail(format!("{} is not a valid rule name", new)));
+    }
+    if let Some(kind) = reserved(old) {
+        return Err(PestError::RenameFail(format!("cannot rename the {} {}", kind, old)));
+    }
+    if let Some(kind) = reserved(new) {
+        return Err(PestError::RenameFail(format!("{} is a {}", new, kind)));
+    }
+
+    Ok(())
+}
+
+/// The kind of the reserved name, if the name is reserved.
+fn reserved(name: &str) -> Option<&'static str> {
+    let spans = match Span::new(name, 0, name.len()) {
+        Some(span) => vec![span],
+        None => return None,
+    };
+
+    if !validator::validate_pest_keywords(&spans).is_empty() {
+        Some("pest keyword")
+    } else if BUILTINS.contains(&name) || pest::unicode::unicode_property_names().any(|property| property == name) {
+        Some("built-in rule")
+    } else {
+        None
+    }
+}
+
+/// The identifiers of the grammar, with whether they are the name of a rule definition.
+fn identifiers(input: &str) -> PestResult<Vec<(Span<'_>, bool)>> {
+    let pairs = match PestParser::parse(Rule::grammar_rules, input) {
+        Ok(pairs) => pairs,
+        Err(e) => return Err(PestError::ParseFail(e.to_string())),
+    };
+
+    let mut identifiers = vec![];
+    for pair in pairs.filter(|pair| pair.as_rule() == Rule::grammar_rule) {
+        let mut inner = pair.into_inner();
+        if let Some(name) = inner.next().filter(|pair| pair.as_rule() == Rule::identifier) {
+            identifiers.push((name.as_span(), true));
+        }
+        identifiers.extend(inner.flatten().filter(|pair| pair.as_rule() == Rule::identifier).map(|pair| (pair.as_span(), false)));
+    }
+
+    Ok(identifiers)
+}
+
+/// Replace the sorted, disjoint ranges of the source with the text.
+fn replace(source: &str, ranges: Vec<(usize, usize)>, text: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut last = 0;
+    for (start, end) in ranges {
+        out.push_str(&source[last..start]);
+        out.push_str(text);
+        last = end;
+    }
+    out.push_str(&source[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_rename_rule() {
+        let input = indoc::indoc! {r#"
+            /// A number
+            number = @{ ASCII_DIGIT+ }
+            list   =  { number ~ ("," ~ number)* ~ #last = number? }
+            // number
+            other  =  { "number" | PUSH(number) | numbers }
+        "#};
+        let expected = indoc::indoc! {r#"
+            /// A number
+            int = @{ ASCII_DIGIT+ }
+            list   =  { int ~ ("," ~ int)* ~ #last = int? }
+            // number
+            other  =  { "number" | PUSH(int) | numbers }
+        "#};
+        assert_eq!(expected, rename_rule(input, "number", "int").unwrap());
+
+        // Only referenced
+        assert_eq!("\u{feff}a = { b ~ c }", rename_rule("\u{feff}a = { number ~ c }", "number", "b").unwrap());
+
+        let error = |old, new| rename_rule(input, old, new).unwrap_err().to_string();
+        assert_eq!("rule list is already defined", error("number", "list"));
+        assert_eq!("1x is not a valid rule name", error("number", "1x"));
+        assert_eq!("a-b is not a valid rule name", error("number", "a-b"));
+        assert_eq!("SOI is a pest keyword", error("number", "SOI"));
+        assert_eq!("PUSH is not a valid rule name", error("number", "PUSH"));
+        assert_eq!("ASCII_DIGIT is a built-in rule", error("number", "ASCII_DIGIT"));
+        assert_eq!("LETTER is a built-in rule", error("number", "LETTER"));
+        assert_eq!("cannot rename the pest keyword ANY", error("ANY", "any"));
+        assert!(matches!(rename_rule("a = {", "a", "b"), Err(PestError::ParseFail(_))));
+    }
+
+    #[test]
+    fn test_rename_rust() {
+        let source = indoc::indoc! {r##"
+            // #[grammar_inline = "b = { \"b\" }"]
+            #[derive(Parser)]
+            #[grammar_inline = "a = { \"a\" ~ b }\nb = { \"b\" }"]
+            struct A;
+
+            const GRAMMAR: &str = "#[grammar_inline = \"b = { \\\"b\\\" }\"]";
+
+            mod b {
+                #[derive(Parser)]
+                #[grammar_inline = r#"
+                b = { "b" }
+                c = { b+ }
+                "#]
+                struct B;
+
+                mod tests {
+                    use super::*;
+                    const _: Rule = Rule::b;
+                }
+            }
+
+            mod other {
+                #[derive(Parser)]
+                #[grammar = "other.pest"]
+                struct Other;
+
+                const _: Rule = Rule::b;
+            }
+
+            fn main() {
+                // Rule::b
+                let _ = (Rule::b, Rule::bb, MyRule::b, "Rule::b", parser::Rule::b);
+            }
+        "##};
+        let expected = indoc::indoc! {r##"
+            // #[grammar_inline = "b = { \"b\" }"]
+            #[derive(Parser)]
+            #[grammar_inline = "a = { \"a\" ~ d }\nd = { \"b\" }"]
+            struct A;
+
+            const GRAMMAR: &str = "#[grammar_inline = \"b = { \\\"b\\\" }\"]";
+
+            mod b {
+                #[derive(Parser)]
+                #[grammar_inline = r#"
+                d = { "b" }
+                c = { d+ }
+                "#]
+                struct B;
+
+                mod tests {
+                    use super::*;
+                    const _: Rule = Rule::b;
+                }
+            }
+
+            mod other {
+                #[derive(Parser)]
+                #[grammar = "other.pest"]
+                struct Other;
+
+                const _: Rule = Rule::b;
+            }
+
+            fn main() {
+                // Rule::b
+                let _ = (Rule::b, Rule::bb, MyRule::b, "Rule::b", parser::Rule::b);
+            }
+        "##};
+        let rename = |paths| rename_rust(source, "b", "d", paths).unwrap();
+        assert_eq!(RustRename { output: expected.to_string(), unresolved: vec![], other_parsers: true }, rename(None));
+
+        let paths = RulePaths { renamed_file: &|_| false, unresolved: false };
+        let renamed = rename(Some(&paths));
+        assert_eq!(expected.replace("const _: Rule = Rule::b;\n    }", "const _: Rule = Rule::d;\n    }").replace("(Rule::b,", "(Rule::d,"), renamed.output);
+        assert_eq!((vec![32], true), (renamed.unresolved, renamed.other_parsers));
+
+        // The grammar file of `Other` is renamed too, and the unresolved paths
+        let paths = RulePaths { renamed_file: &|file| file == "other.pest", unresolved: true };
+        let renamed = rename(Some(&paths));
+        assert_eq!(source.replace("Rule::b;", "Rule::d;").replace("(Rule::b,", "(Rule::d,").replace("parser::Rule::b", "parser::Rule::d").replace("~ b }\\nb =", "~ d }\\nd =").replace("b = { \"b\" }\n    c = { b+ }", "d = { \"b\" }\n    c = { d+ }"), renamed.output);
+        assert_eq!((vec![32], false), (renamed.unresolved, renamed.other_parsers));
+
+        assert_eq!("rule c is already defined", rename_rust(source, "b", "c", None).unwrap_err().to_string());
+        assert_eq!("rule b is escaped in the inline grammar", rename_rust(&source.replace("~ b }", "~ \\x62 }"), "b", "d", None).unwrap_err().to_string());
+
+        assert_eq!(vec!["a = { \"a\" ~ b }\nb = { \"b\" }", "\n    b = { \"b\" }\n    c = { b+ }\n    "], inline_grammars(source));
+        assert_eq!(vec!["b", "c"], defined_rules(&inline_grammars(source)[1]).unwrap());
+    }
+}
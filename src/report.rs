@@ -0,0 +1,197 @@
+use pest_fmt::{json, PestError, PestResult};
+use std::path::PathBuf;
+
+/// The format of the messages of the CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum MessageFormat {
+    /// Text for humans
+    Human,
+    /// One JSON object per line for each file, then a summary object
+    Json,
+}
+
+/// The result of a file.
+#[derive(Debug)]
+pub(crate) enum Status {
+    Unchanged,
+    Formatted {
+        input: String,
+        output: String,
+    },
+    /// Not formatted, e.g.: not changed in git with `--changed`
+    Skipped,
+    Error(PestError),
+}
+
+#[derive(Debug)]
+pub(crate) struct FileReport {
+    pub path: PathBuf,
+    pub status: Status,
+}
+
+impl FileReport {
+    /// Create the report from the `(input, output)` of `format_file`.
+    pub(crate) fn new(path: PathBuf, result: PestResult<(String, String)>) -> FileReport {
+        let status = match result {
+            Ok((input, output)) if input == output => Status::Unchanged,
+            Ok((input, output)) => Status::Formatted { input, output },
+            Err(e) => Status::Error(e),
+        };
+
+        FileReport { path, status }
+    }
+
+    fn status_name(&self) -> &'static str {
+        match self.status {
+            Status::Unchanged => "unchanged",
+            Status::Formatted { .. } => "formatted",
+            Status::Skipped => "skipped",
+            Status::Error(_) => "error",
+        }
+    }
+
+    /// The JSON object of the file, with the edits of the formatted file if `edits` is set.
+    fn to_json(&self, edits: bool) -> String {
+        let mut json = format!(r#"{{"type":"file","path":{},"status":"{}""#, json::quote(&self.path.to_string_lossy()), self.status_name());
+
+        match &self.status {
+            Status::Formatted { input, output } if edits => {
+                let edits: Vec<String> = diff_lines(input, output).iter().map(Edit::to_json).collect();
+                json.push_str(&format!(r#","edits":[{}]"#, edits.join(",")));
+            }
+            Status::Error(e) => {
+                let (line, column) = match e.line_col() {
+                    Some((line, column)) => (line.to_string(), column.to_string()),
+                    None => ("null".to_string(), "null".to_string()),
+                };
+                json.push_str(&format!(r#","error":{{"line":{},"column":{},"message":{}}}"#, line, column, json::quote(&e.to_string())));
+            }
+            _ => (),
+        }
+
+        json.push('}');
+        json
+    }
+}
+
+/// Print the reports of the files and the summary.
+pub(crate) fn print_reports(reports: &[FileReport], format: MessageFormat, edits: bool) {
+    let count = |status: &str| reports.iter().filter(|report| report.status_name() == status).count();
+
+    match format {
+        MessageFormat::Human => {
+            for report in reports {
+                if let Status::Error(e) = &report.status {
+                    eprintln!("{}: {}", report.path.display(), e);
+                }
+            }
+            println!("Formatted {} files", count("formatted"));
+        }
+        MessageFormat::Json => {
+            for report in reports {
+                println!("{}", report.to_json(edits));
+            }
+            println!(r#"{{"type":"summary","files":{},"unchanged":{},"formatted":{},"skipped":{},"errors":{}}}"#, reports.len(), count("unchanged"), count("formatted"), count("skipped"), count("error"));
+        }
+    }
+}
+
+/// Replace the lines `start_line..end_line` (1-based, end exclusive) of the input with the `text`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Edit {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+impl Edit {
+    fn to_json(&self) -> String {
+        format!(r#"{{"start_line":{},"end_line":{},"text":{}}}"#, self.start_line, self.end_line, json::quote(&self.text))
+    }
+}
+
+/// Above this number of cells of the LCS table, the changed lines are replaced in one edit.
+const MAX_DIFF_CELLS: usize = 1 << 22;
+
+/// The line edits to turn the input into the output.
+pub(crate) fn diff_lines(input: &str, output: &str) -> Vec<Edit> {
+    let a: Vec<&str> = input.split_inclusive('\n').collect();
+    let b: Vec<&str> = output.split_inclusive('\n').collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    if a.is_empty() && b.is_empty() {
+        return vec![];
+    }
+    if (a.len() + 1) * (b.len() + 1) > MAX_DIFF_CELLS {
+        return vec![Edit { start_line: prefix + 1, end_line: prefix + a.len() + 1, text: b.concat() }];
+    }
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..].
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] { lcs[(i + 1) * width + j + 1] + 1 } else { lcs[(i + 1) * width + j].max(lcs[i * width + j + 1]) };
+        }
+    }
+
+    let mut edits = vec![];
+    let mut edit: Option<Edit> = None;
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            edits.extend(edit.take());
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let current = edit.get_or_insert_with(|| Edit { start_line: prefix + i + 1, end_line: prefix + i + 1, text: String::new() });
+        if j == b.len() || (i < a.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+            i += 1;
+            current.end_line = prefix + i + 1;
+        } else {
+            current.text.push_str(b[j]);
+            j += 1;
+        }
+    }
+    edits.extend(edit);
+
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format;
+    use pest_fmt::Config;
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(Vec::<Edit>::new(), diff_lines("a\nb\n", "a\nb\n"));
+        assert_eq!(vec![Edit { start_line: 2, end_line: 3, text: "B\n".to_string() }], diff_lines("a\nb\nc\n", "a\nB\nc\n"));
+        assert_eq!(vec![Edit { start_line: 1, end_line: 2, text: "A\n".to_string() }, Edit { start_line: 3, end_line: 3, text: "x\ny\n".to_string() }, Edit { start_line: 4, end_line: 5, text: "".to_string() }], diff_lines("a\nb\nc\nd\ne\n", "A\nb\nx\ny\nc\ne\n"));
+        assert_eq!(vec![Edit { start_line: 2, end_line: 2, text: "b".to_string() }], diff_lines("a\n", "a\nb"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let input = "a={\"a\"}\nb = { \"b\" }\n";
+        let report = FileReport::new(PathBuf::from("a.pest"), format(input, &Config::default()).map(|output| (input.to_string(), output)));
+        assert_eq!(r#"{"type":"file","path":"a.pest","status":"formatted"}"#, report.to_json(false));
+        assert_eq!(r#"{"type":"file","path":"a.pest","status":"formatted","edits":[{"start_line":1,"end_line":2,"text":"a = { \"a\" }\n"}]}"#, report.to_json(true));
+
+        let report = FileReport::new(PathBuf::from("b.pest"), format("b = {", &Config::default()).map(|output| (output.clone(), output)));
+        let json = report.to_json(true);
+        assert!(json.starts_with(r#"{"type":"file","path":"b.pest","status":"error","error":{"line":1,"column":6,"message":" --> 1:6"#), "{}", json);
+
+        let report = FileReport { path: PathBuf::from("c.pest"), status: Status::Skipped };
+        assert_eq!(r#"{"type":"file","path":"c.pest","status":"skipped"}"#, report.to_json(true));
+
+        let report = FileReport::new(PathBuf::from("d.pest"), Err(PestError::IOError("no such file or directory".to_string())));
+        assert_eq!(r#"{"type":"file","path":"d.pest","status":"error","error":{"line":null,"column":null,"message":"no such file or directory"}}"#, report.to_json(true));
+    }
+}
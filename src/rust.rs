@@ -0,0 +1,399 @@
+//! A light scan of the Rust sources, for the `#[derive(Parser)]` items and the `Rule::name` paths.
+//!
+//! The comments and literals are skipped, but the names are not resolved: the `Rule` of a path is the one of
+//! the closest module around it with a parser, see `Source::parser_at`.
+
+/// A string literal of the Rust source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Literal {
+    /// The range of the literal, with the quotes
+    pub range: (usize, usize),
+    /// The value, with the escapes resolved
+    pub value: String,
+    /// The offset in the source of each byte of the value, and of the closing quote
+    pub offsets: Vec<usize>,
+}
+
+/// An item with `#[derive(Parser)]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Parser {
+    /// The offset of the first attribute of the item
+    pub start: usize,
+    /// The literals of the `#[grammar_inline = "..."]` attributes
+    pub inline: Vec<Literal>,
+    /// The paths of the `#[grammar = "..."]` attributes
+    pub files: Vec<String>,
+}
+
+pub(crate) struct Source<'s> {
+    source: &'s str,
+    /// The source with the comments and the contents of the literals replaced by spaces, at the same offsets
+    code: String,
+    /// The ranges of the bodies of the `mod name { ... }` modules, from `{` to `}`
+    modules: Vec<(usize, usize)>,
+    pub parsers: Vec<Parser>,
+}
+
+impl<'s> Source<'s> {
+    pub(crate) fn new(source: &'s str) -> Source<'s> {
+        let mut rust = Source { source, code: mask(source), modules: vec![], parsers: vec![] };
+        rust.modules = rust.find_modules();
+        rust.parsers = rust.find_parsers();
+        rust
+    }
+
+    /// The ranges of the names of the `Rule::name` paths, with whether the `Rule` is qualified, e.g.:
+    /// `parser::Rule::name`.
+    pub(crate) fn rule_paths(&self, name: &str) -> Vec<(usize, usize, bool)> {
+        self.all_rule_paths().into_iter().filter(|(start, end, _)| &self.code[*start..*end] == name).collect()
+    }
+
+    /// The ranges of the names of every `Rule::name` path, see `rule_paths`.
+    pub(crate) fn all_rule_paths(&self) -> Vec<(usize, usize, bool)> {
+        let mut paths = vec![];
+        for start in self.words("Rule") {
+            let rest = &self.code[start + "Rule".len()..];
+            let rest = match rest.trim_start().strip_prefix("::") {
+                Some(rest) => rest.trim_start(),
+                None => continue,
+            };
+            let len = rest.len() - rest.trim_start_matches(is_ident).len();
+            if len > 0 {
+                let name_start = self.code.len() - rest.len();
+                let qualified = self.code[..start].trim_end().ends_with("::");
+                paths.push((name_start, name_start + len, qualified));
+            }
+        }
+
+        paths
+    }
+
+    /// The parsers of the closest module around the offset with a parser, the file is a module too.
+    ///
+    /// The `Rule` of a parser is usually imported by the child modules with `use super::*`.
+    pub(crate) fn parser_at(&self, offset: usize) -> Vec<&Parser> {
+        let mut scopes: Vec<Option<(usize, usize)>> = self.modules.iter().filter(|(start, end)| *start < offset && offset < *end).copied().map(Some).collect();
+        scopes.sort_by_key(|scope| scope.map(|(start, end)| end - start));
+        scopes.push(None);
+
+        for scope in scopes {
+            let parsers: Vec<&Parser> = self.parsers.iter().filter(|parser| self.module_at(parser.start) == scope).collect();
+            if !parsers.is_empty() {
+                return parsers;
+            }
+        }
+
+        vec![]
+    }
+
+    /// The innermost module around the offset, `None` for the file.
+    fn module_at(&self, offset: usize) -> Option<(usize, usize)> {
+        self.modules.iter().filter(|(start, end)| *start < offset && offset < *end).min_by_key(|(start, end)| end - start).copied()
+    }
+
+    /// The offsets of the word in the code.
+    fn words<'a>(&'a self, word: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.code.match_indices(word).map(|(i, _)| i).filter(move |i| !self.code[..*i].ends_with(is_ident) && !self.code[i + word.len()..].starts_with(is_ident))
+    }
+
+    /// The offset after the matching closing bracket of the opening bracket at `open`.
+    fn close(&self, open: usize) -> Option<usize> {
+        let bytes = self.code.as_bytes();
+        let (left, right) = (bytes[open], if bytes[open] == b'{' { b'}' } else { b']' });
+        let mut depth = 0;
+        for (i, byte) in bytes.iter().enumerate().skip(open) {
+            if *byte == left {
+                depth += 1;
+            } else if *byte == right {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn find_modules(&self) -> Vec<(usize, usize)> {
+        let mut modules = vec![];
+        for start in self.words("mod") {
+            let rest = self.code[start + "mod".len()..].trim_start();
+            let name = rest.len() - rest.trim_start_matches(is_ident).len();
+            if name > 0 && rest[name..].trim_start().starts_with('{') {
+                let open = self.code.len() - rest[name..].trim_start().len();
+                if let Some(close) = self.close(open) {
+                    modules.push((open, close));
+                }
+            }
+        }
+
+        modules
+    }
+
+    fn find_parsers(&self) -> Vec<Parser> {
+        let mut parsers = vec![];
+        let mut i = 0;
+        while let Some(found) = self.code[i..].find('#') {
+            let start = i + found;
+            i = start + 1;
+
+            // The outer attributes of an item, one after another.
+            let mut attributes = vec![];
+            let mut end = start;
+            loop {
+                let rest = &self.code[end..];
+                let open = match rest.trim_start().strip_prefix('#').map(str::trim_start) {
+                    Some(attribute) if attribute.starts_with('[') => self.code.len() - attribute.len(),
+                    _ => break,
+                };
+                match self.close(open) {
+                    Some(close) => {
+                        attributes.push((open + 1, close - 1));
+                        end = close;
+                    }
+                    None => break,
+                }
+            }
+            if attributes.is_empty() {
+                continue;
+            }
+            i = end;
+
+            let derive = attributes.iter().any(|(start, end)| {
+                let attribute = self.code[*start..*end].trim_start();
+                attribute.starts_with("derive") && attribute["derive".len()..].trim_start().starts_with('(') && attribute.split(|c| !is_ident(c)).any(|word| word == "Parser")
+            });
+            if !derive {
+                continue;
+            }
+
+            let mut parser = Parser { start, inline: vec![], files: vec![] };
+            for (start, end) in attributes {
+                let attribute = self.code[start..end].trim_start();
+                let name = attribute.len() - attribute.trim_start_matches(is_ident).len();
+                let value = match attribute[name..].trim_start().strip_prefix('=') {
+                    Some(value) => end - value.trim_start().len(),
+                    None => continue,
+                };
+                match (&attribute[..name], string_literal(self.source, value)) {
+                    ("grammar_inline", Some(literal)) => parser.inline.push(literal),
+                    ("grammar", Some(literal)) => parser.files.push(literal.value),
+                    _ => (),
+                }
+            }
+            parsers.push(parser);
+        }
+
+        parsers
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+/// Replace the comments and the contents of the string and char literals by spaces, the line breaks are kept.
+fn mask(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut code = bytes.to_vec();
+    let mut blank = |start: usize, end: usize| {
+        for byte in &mut code[start..end] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let ident_before = i > 0 && (bytes[i - 1] == b'_' || bytes[i - 1].is_ascii_alphanumeric());
+        let end = match &bytes[i..] {
+            [b'/', b'/', ..] => {
+                let end = source[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                blank(i, end);
+                end
+            }
+            [b'/', b'*', ..] => {
+                let mut depth = 0;
+                let mut j = i;
+                while j < bytes.len() {
+                    match &bytes[j..] {
+                        [b'/', b'*', ..] => (depth, j) = (depth + 1, j + 2),
+                        [b'*', b'/', ..] => (depth, j) = (depth - 1, j + 2),
+                        _ => j += 1,
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                blank(i, j);
+                j
+            }
+            [b'r', b'"' | b'#', ..] | [b'b', b'r', b'"' | b'#', ..] if !ident_before => {
+                let open = i + source[i..].find(['"', '#']).unwrap_or(0);
+                let hashes = source[open..].len() - source[open..].trim_start_matches('#').len();
+                if bytes.get(open + hashes) == Some(&b'"') {
+                    let close = format!("\"{}", "#".repeat(hashes));
+                    let end = source[open + hashes + 1..].find(&close).map_or(bytes.len(), |end| open + hashes + 1 + end + close.len());
+                    blank(open + hashes + 1, end.saturating_sub(close.len()).max(open + hashes + 1));
+                    end
+                } else {
+                    i + 1
+                }
+            }
+            [b'"', ..] | [b'b', b'"', ..] => {
+                let open = i + source[i..].find('"').unwrap_or(0);
+                let mut j = open + 1;
+                while j < bytes.len() && bytes[j] != b'"' {
+                    j += if bytes[j] == b'\\' { 2 } else { 1 };
+                }
+                blank(open + 1, j.min(bytes.len()));
+                j + 1
+            }
+            [b'\'', b'\\', ..] => {
+                let end = source[i + 2..].find('\'').map_or(bytes.len(), |end| i + 2 + end + 1);
+                blank(i + 1, end - 1);
+                end
+            }
+            // A char literal, or else a lifetime.
+            [b'\'', ..] => match source[i + 1..].chars().next() {
+                Some(c) if source[i + 1 + c.len_utf8()..].starts_with('\'') => {
+                    blank(i + 1, i + 1 + c.len_utf8());
+                    i + 2 + c.len_utf8()
+                }
+                _ => i + 1,
+            },
+            _ => i + 1,
+        };
+        i = end.max(i + 1);
+    }
+
+    String::from_utf8(code).unwrap_or_default()
+}
+
+/// Parse the normal or raw string literal at `start`.
+pub(crate) fn string_literal(source: &str, start: usize) -> Option<Literal> {
+    let rest = &source[start..];
+
+    if let Some(raw) = rest.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let open = 1 + hashes + 1;
+        if !rest[1 + hashes..].starts_with('"') {
+            return None;
+        }
+        let close = format!("\"{}", "#".repeat(hashes));
+        let len = rest[open..].find(&close)?;
+        let value = rest[open..open + len].to_string();
+        let offsets = (start + open..=start + open + len).collect();
+        return Some(Literal { range: (start, start + open + len + close.len()), value, offsets });
+    }
+
+    let body = rest.strip_prefix('"')?;
+    let mut value = String::new();
+    let mut offsets = vec![];
+    let mut chars = body.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let offset = start + 1 + i;
+        let c = match c {
+            '"' => {
+                offsets.push(offset);
+                return Some(Literal { range: (start, offset + 1), value, offsets });
+            }
+            '\\' => match chars.next()?.1 {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '0' => '\0',
+                'x' => {
+                    let hex: String = (0..2).filter_map(|_| chars.next()).map(|(_, c)| c).collect();
+                    char::from(u8::from_str_radix(&hex, 16).ok()?)
+                }
+                'u' => {
+                    let mut hex = String::new();
+                    for (_, c) in chars.by_ref() {
+                        match c {
+                            '{' => (),
+                            '}' => break,
+                            c => hex.push(c),
+                        }
+                    }
+                    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                }
+                '\n' | '\r' => {
+                    while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                    continue;
+                }
+                c => c,
+            },
+            c => c,
+        };
+        offsets.extend(std::iter::repeat(offset).take(c.len_utf8()));
+        value.push(c);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const SOURCE: &str = indoc::indoc! {r##"
+        // #[derive(Parser)]
+        // #[grammar_inline = "a = { \"a\" }"]
+        /// ```
+        /// let _ = Rule::a;
+        /// ```
+        #[derive(Parser, Debug)]
+        #[grammar = "a.pest"]
+        #[grammar_inline = r#"b = { "b" }"#]
+        pub struct A;
+
+        #[derive(Debug)]
+        #[grammar_inline = "c = { \"c\" }"]
+        struct NotParser;
+
+        mod b {
+            #[derive(pest_derive::Parser)]
+            #[grammar_inline = "a = { \"a\" }"]
+            pub(crate) struct B;
+
+            mod tests {
+                use super::*;
+                const S: &str = "Rule::a /* }";
+                fn f<'a>(c: char) -> bool { c == '{' || c == '\'' || Rule :: a == parser::Rule::a }
+            }
+        }
+
+        fn main() {
+            let _ = (Rule::a, Rule::ab, MyRule::a, '"');
+        }
+    "##};
+
+    #[test]
+    fn test_parsers() {
+        let rust = Source::new(SOURCE);
+        assert_eq!(2, rust.parsers.len());
+        assert_eq!(vec!["a.pest".to_string()], rust.parsers[0].files);
+        assert_eq!(vec!["b = { \"b\" }"], rust.parsers[0].inline.iter().map(|literal| literal.value.as_str()).collect::<Vec<_>>());
+        assert_eq!(vec!["a = { \"a\" }"], rust.parsers[1].inline.iter().map(|literal| literal.value.as_str()).collect::<Vec<_>>());
+        assert_eq!(1, rust.modules.iter().filter(|(start, _)| SOURCE[..*start].ends_with("mod b ")).count());
+    }
+
+    #[test]
+    fn test_rule_paths() {
+        let rust = Source::new(SOURCE);
+        let paths = rust.rule_paths("a");
+        let lines: Vec<(usize, bool)> = paths.iter().map(|(start, _, qualified)| (SOURCE[..*start].lines().count(), *qualified)).collect();
+        assert_eq!(vec![(23, false), (23, true), (28, false)], lines);
+
+        let parser = |i: usize| rust.parser_at(paths[i].0).into_iter().map(|parser| parser.start).collect::<Vec<_>>();
+        assert_eq!(vec![rust.parsers[1].start], parser(0));
+        assert_eq!(vec![rust.parsers[0].start], parser(2));
+
+        let names: Vec<&str> = rust.all_rule_paths().into_iter().map(|(start, end, _)| &SOURCE[start..end]).collect();
+        assert_eq!(vec!["a", "a", "a", "ab"], names);
+    }
+}
@@ -0,0 +1,294 @@
+//! An approximation of the depth of the stack of `PUSH`, `POP`, `DROP` and `PEEK` along the paths of the
+//! rules, for the lints.
+//!
+//! Each rule is summed up by the depth it needs on entry and the change of the depth, computed until they
+//! don't change. A rule that is called by other rules may rely on the values pushed by its callers, so the
+//! missing values are reported at the rules that are not called by others, or after a `POP_ALL`.
+
+use crate::ast::{Expr, ExprKind, Grammar, Span};
+use std::collections::{HashMap, HashSet};
+
+/// The summaries of recursive rules may change on every iteration, e.g.: `a = { PUSH("a") ~ a? }`.
+const MAX_ITERATIONS: usize = 16;
+/// From this iteration on, the maximum depth of a changing summary is unbounded.
+const WIDEN_ITERATION: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProblemKind {
+    /// `POP`, `DROP` or `PEEK` without a value pushed before on some path
+    Underflow,
+    /// A repetition that pushes more values than it pops
+    UnbalancedPush,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Problem {
+    pub kind: ProblemKind,
+    pub message: String,
+    pub span: Span,
+}
+
+/// The depth of the stack at a point of a rule, relative to the start of the rule, or to the empty stack
+/// after a `POP_ALL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Depth {
+    lo: i64,
+    /// `None` if unbounded
+    hi: Option<i64>,
+    /// Relative to the empty stack
+    absolute: bool,
+}
+
+impl Depth {
+    const ZERO: Depth = Depth { lo: 0, hi: Some(0), absolute: false };
+
+    fn shift(self, lo: i64, hi: Option<i64>) -> Depth {
+        Depth { lo: self.lo + lo, hi: self.hi.zip(hi).map(|(a, b)| a + b), absolute: self.absolute }
+    }
+
+    fn join(self, other: Depth) -> Depth {
+        let hi = self.hi.zip(other.hi).map(|(a, b)| a.max(b));
+        Depth { lo: self.lo.min(other.lo), hi, absolute: self.absolute && other.absolute }
+    }
+}
+
+/// The effect of a rule on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Summary {
+    /// The values that must be on the stack on entry
+    need: i64,
+    /// The depth at the end, relative to the entry, or absolute after a `POP_ALL`
+    end: Depth,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Summary { need: 0, end: Depth::ZERO }
+    }
+}
+
+struct Analysis<'g> {
+    input: &'g str,
+    grammar: &'g Grammar,
+    summaries: HashMap<&'g str, Summary>,
+    /// The values that the current rule needs on entry
+    need: i64,
+    /// Report the missing values of the current rule instead of adding them to `need`
+    report_missing: bool,
+    /// `None` while computing the summaries
+    problems: Option<Vec<Problem>>,
+}
+
+/// Check the stack operations of the grammar.
+pub(crate) fn check(grammar: &Grammar, input: &str) -> Vec<Problem> {
+    let mut analysis = Analysis { input, grammar, summaries: HashMap::new(), need: 0, report_missing: false, problems: None };
+
+    for iteration in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for rule in &grammar.rules {
+            let mut summary = analysis.summarize(&rule.expr);
+            let old = analysis.summaries.get(rule.name.as_str()).copied().unwrap_or_default();
+            if summary != old && iteration >= WIDEN_ITERATION {
+                summary.end.hi = None;
+            }
+            if summary != old {
+                analysis.summaries.insert(&rule.name, summary);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // The rules called by other rules may use the values pushed by their callers.
+    let called: HashSet<&str> = grammar.rules.iter().flat_map(|rule| rule.references.iter().filter(move |name| **name != rule.name).map(String::as_str)).collect();
+
+    analysis.problems = Some(vec![]);
+    for rule in &grammar.rules {
+        analysis.report_missing = !called.contains(rule.name.as_str());
+        analysis.summarize(&rule.expr);
+    }
+
+    let mut problems = analysis.problems.unwrap_or_default();
+    problems.sort_by_key(|problem| (problem.span.start, problem.kind == ProblemKind::Underflow));
+    problems.dedup_by_key(|problem| (problem.span.start, problem.kind));
+    problems
+}
+
+impl<'g> Analysis<'g> {
+    fn summarize(&mut self, expr: &'g Expr) -> Summary {
+        self.need = 0;
+        let end = self.eval(expr, Depth::ZERO);
+        Summary { need: self.need, end }
+    }
+
+    /// The depth after the expression matches.
+    fn eval(&mut self, expr: &'g Expr, depth: Depth) -> Depth {
+        match &expr.kind {
+            ExprKind::Str(_) | ExprKind::Insens(_) | ExprKind::Range(..) => depth,
+            ExprKind::Ident(name) => match name.as_str() {
+                "POP" | "DROP" => {
+                    self.require(1, depth, expr);
+                    depth.shift(-1, Some(-1))
+                }
+                "PEEK" => {
+                    self.require(1, depth, expr);
+                    depth
+                }
+                "POP_ALL" => Depth { lo: 0, hi: Some(0), absolute: true },
+                _ => match self.summaries.get(name.as_str()).copied() {
+                    Some(summary) => {
+                        self.require(summary.need, depth, expr);
+                        match summary.end.absolute {
+                            true => summary.end,
+                            false => depth.shift(summary.end.lo, summary.end.hi),
+                        }
+                    }
+                    // Builtin or undefined
+                    None => depth,
+                },
+            },
+            ExprKind::PeekSlice(start, end) => {
+                let bound = |index: &Option<i32>| index.map_or(0, |index| i64::from(index).abs());
+                self.require(bound(start).max(bound(end)), depth, expr);
+                depth
+            }
+            ExprKind::Push(expr) => self.eval(expr, depth).shift(1, Some(1)),
+            // The lookahead restores the stack.
+            ExprKind::PosPred(expr) | ExprKind::NegPred(expr) => {
+                self.eval(expr, depth);
+                depth
+            }
+            ExprKind::Tag(_, expr) => self.eval(expr, depth),
+            ExprKind::Seq(exprs) => exprs.iter().fold(depth, |depth, expr| self.eval(expr, depth)),
+            ExprKind::Choice(exprs) => exprs.iter().map(|expr| self.eval(expr, depth)).reduce(Depth::join).unwrap_or(depth),
+            ExprKind::Opt(body) => self.repeat(expr, body, depth, 0, Some(1)),
+            ExprKind::Rep(body) => self.repeat(expr, body, depth, 0, None),
+            ExprKind::RepOnce(body) => self.repeat(expr, body, depth, 1, None),
+            ExprKind::RepExact(body, n) => self.repeat(expr, body, depth, *n, Some(*n)),
+            ExprKind::RepMin(body, min) => self.repeat(expr, body, depth, *min, None),
+            ExprKind::RepMax(body, max) => self.repeat(expr, body, depth, 0, Some(*max)),
+            ExprKind::RepMinMax(body, min, max) => self.repeat(expr, body, depth, *min, Some(*max)),
+        }
+    }
+
+    /// The depth after `min..=max` matches of the body.
+    fn repeat(&mut self, expr: &'g Expr, body: &'g Expr, depth: Depth, min: u32, max: Option<u32>) -> Depth {
+        let once = self.eval(body, depth);
+        if max.map_or(true, |max| max >= 2) {
+            // The second match finds the underflows of the values popped by the first one.
+            self.eval(body, once);
+        }
+
+        let step = self.quietly(|analysis| analysis.eval(body, Depth::ZERO));
+        if step.absolute {
+            return if min == 0 { depth.join(once) } else { once };
+        }
+        // An unbounded growth comes from a rule or a nested repetition, which is reported there.
+        if step.hi.is_some_and(|hi| hi > 0) && max.is_none() {
+            let message = "the stack grows with each repetition, a value is pushed and not popped on some path".to_string();
+            self.report(ProblemKind::UnbalancedPush, message, expr);
+        }
+
+        let (min, max) = (i64::from(min), max.map(i64::from));
+        let lo = match (step.lo >= 0, max) {
+            (true, _) => step.lo * min,
+            (false, Some(max)) => step.lo * max,
+            // The underflow is reported already.
+            (false, None) => step.lo * min.max(2),
+        };
+        let hi = match step.hi {
+            Some(hi) if hi > 0 => max.map(|max| hi * max),
+            Some(hi) => Some(hi * min),
+            None => None,
+        };
+        depth.shift(lo, hi)
+    }
+
+    /// Run the analysis without reporting the problems or changing the needs of the rule.
+    fn quietly(&mut self, f: impl FnOnce(&mut Self) -> Depth) -> Depth {
+        let (need, problems) = (self.need, self.problems.take());
+        let depth = f(self);
+        self.need = need;
+        self.problems = problems;
+        depth
+    }
+
+    /// The expression needs `n` values on the stack.
+    fn require(&mut self, n: i64, depth: Depth, expr: &'g Expr) {
+        let missing = n - depth.lo;
+        if missing <= 0 {
+            return;
+        }
+
+        if depth.absolute || self.report_missing {
+            let text = &self.input[expr.span.start..expr.span.end];
+            let message = match &expr.kind {
+                ExprKind::Ident(name) if self.grammar.get(name).is_some() => format!("rule {} may underflow the stack, it pops or peeks values which are not pushed before on some path", name),
+                _ => format!("{} may underflow the stack, no value is pushed before it on some path", text),
+            };
+            self.report(ProblemKind::Underflow, message, expr);
+        } else {
+            self.need = self.need.max(missing);
+        }
+    }
+
+    fn report(&mut self, kind: ProblemKind, message: String, expr: &Expr) {
+        if let Some(problems) = &mut self.problems {
+            problems.push(Problem { kind, message, span: expr.span });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_source(input: &str) -> Vec<(ProblemKind, usize, usize)> {
+        check(&Grammar::parse(input).unwrap(), input).into_iter().map(|problem| (problem.kind, problem.span.line, problem.span.col)).collect()
+    }
+
+    #[test]
+    fn test_balanced() {
+        let source = indoc::indoc! {r##"
+            raw_string = { "r" ~ PUSH("#"*) ~ "\"" ~ (!("\"" ~ PEEK) ~ ANY)* ~ "\"" ~ POP }
+            indented = { PUSH(" "+) ~ line ~ (PEEK[..] ~ line)* ~ DROP }
+            line = { (!NEWLINE ~ ANY)* ~ NEWLINE }
+            nested = { PUSH("(") ~ nested? ~ POP }
+            open = { PUSH("(") }
+            close = { POP }
+            block = { open ~ "x" ~ close }
+            list = { (PUSH("a") ~ POP)* }
+            all = { PUSH("a") ~ PUSH("b") ~ POP_ALL }
+        "##};
+        assert_eq!(Vec::<(ProblemKind, usize, usize)>::new(), check_source(source));
+    }
+
+    #[test]
+    fn test_underflow() {
+        let source = indoc::indoc! {r#"
+            a = { POP }
+            b = { PUSH("b") | "c" }
+            c = { b ~ PEEK ~ POP_ALL ~ DROP }
+            d = { PUSH("d") ~ PEEK[0..2] }
+            e = { f ~ PUSH("e") }
+            f = { POP }
+            g = { PUSH("g") ~ ("x" ~ POP)+ }
+        "#};
+        let problems = check(&Grammar::parse(source).unwrap(), source);
+        let problems: Vec<(ProblemKind, &str, usize, usize)> = problems.iter().map(|problem| (problem.kind, problem.message.as_str(), problem.span.line, problem.span.col)).collect();
+        assert_eq!(vec![(ProblemKind::Underflow, "POP may underflow the stack, no value is pushed before it on some path", 1, 7), (ProblemKind::Underflow, "PEEK may underflow the stack, no value is pushed before it on some path", 3, 11), (ProblemKind::Underflow, "DROP may underflow the stack, no value is pushed before it on some path", 3, 28), (ProblemKind::Underflow, "PEEK[0..2] may underflow the stack, no value is pushed before it on some path", 4, 19), (ProblemKind::Underflow, "rule f may underflow the stack, it pops or peeks values which are not pushed before on some path", 5, 7), (ProblemKind::Underflow, "POP may underflow the stack, no value is pushed before it on some path", 7, 26),], problems);
+    }
+
+    #[test]
+    fn test_unbalanced_push() {
+        let source = indoc::indoc! {r#"
+            a = { (PUSH("a") ~ "b")* ~ POP_ALL }
+            b = { ("x" | PUSH("b"))+ }
+            c = { PUSH("c"){3} ~ POP ~ POP ~ POP }
+            d = { PUSH("d") ~ d? }
+        "#};
+        assert_eq!(vec![(ProblemKind::UnbalancedPush, 1, 7), (ProblemKind::UnbalancedPush, 2, 7)], check_source(source));
+    }
+}
@@ -0,0 +1,172 @@
+//! Statistics of a grammar, in text or JSON.
+
+use crate::{
+    ast::{Expr, ExprKind, Grammar, RuleType},
+    json, PestResult,
+};
+use std::fmt;
+
+/// The statistics of a grammar.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub rules: usize,
+    /// The rules without a modifier
+    pub normal: usize,
+    /// `_`
+    pub silent: usize,
+    /// `@`
+    pub atomic: usize,
+    /// `$`
+    pub compound_atomic: usize,
+    /// `!`
+    pub non_atomic: usize,
+    /// `#tag = e`
+    pub tags: usize,
+    /// `PUSH(e)`
+    pub push: usize,
+    /// `POP` and `POP_ALL`
+    pub pop: usize,
+    /// `PEEK`, `PEEK_ALL` and `PEEK[..]`
+    pub peek: usize,
+    /// `DROP`
+    pub drop: usize,
+    /// The rule with the most nested expression, and the depth
+    pub max_depth: Option<(String, usize)>,
+    /// The rule with the most lines, and the lines
+    pub longest_rule: Option<(String, usize)>,
+    /// The alternatives of all the choices
+    pub alternatives: usize,
+    /// The rules without `///` doc comments
+    pub missing_docs: Vec<String>,
+}
+
+impl Stats {
+    pub fn new(input: &str) -> PestResult<Stats> {
+        let input = input.trim_start_matches('\u{feff}');
+        let grammar = Grammar::parse(input)?;
+
+        let mut stats = Stats { rules: grammar.rules.len(), ..Stats::default() };
+        for rule in &grammar.rules {
+            match rule.ty {
+                RuleType::Normal => stats.normal += 1,
+                RuleType::Silent => stats.silent += 1,
+                RuleType::Atomic => stats.atomic += 1,
+                RuleType::CompoundAtomic => stats.compound_atomic += 1,
+                RuleType::NonAtomic => stats.non_atomic += 1,
+            }
+
+            rule.expr.walk(&mut |expr| match &expr.kind {
+                ExprKind::Tag(..) => stats.tags += 1,
+                ExprKind::Push(_) => stats.push += 1,
+                ExprKind::Ident(name) if name == "POP" || name == "POP_ALL" => stats.pop += 1,
+                ExprKind::Ident(name) if name == "PEEK" || name == "PEEK_ALL" => stats.peek += 1,
+                ExprKind::PeekSlice(..) => stats.peek += 1,
+                ExprKind::Ident(name) if name == "DROP" => stats.drop += 1,
+                ExprKind::Choice(exprs) => stats.alternatives += exprs.len(),
+                _ => (),
+            });
+
+            let depth = depth(&rule.expr);
+            if stats.max_depth.as_ref().map_or(true, |(_, max)| depth > *max) {
+                stats.max_depth = Some((rule.name.clone(), depth));
+            }
+            let lines = input[rule.span.start..rule.end].lines().count();
+            if stats.longest_rule.as_ref().map_or(true, |(_, max)| lines > *max) {
+                stats.longest_rule = Some((rule.name.clone(), lines));
+            }
+
+            // Without docs, the rule starts at the identifier.
+            if rule.start == rule.span.start {
+                stats.missing_docs.push(rule.name.clone());
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// The statistics as a JSON object.
+    pub fn to_json(&self) -> String {
+        let rule_count = |value: &Option<(String, usize)>, key: &str| match value {
+            Some((rule, count)) => format!(r#"{{"rule":{},"{}":{}}}"#, json::quote(rule), key, count),
+            None => "null".to_string(),
+        };
+        let missing_docs: Vec<String> = self.missing_docs.iter().map(|name| json::quote(name)).collect();
+
+        format!(r#"{{"rules":{{"total":{},"normal":{},"silent":{},"atomic":{},"compound_atomic":{},"non_atomic":{}}},"tags":{},"stack":{{"push":{},"pop":{},"peek":{},"drop":{}}},"max_depth":{},"longest_rule":{},"alternatives":{},"docs":{{"documented":{},"missing":[{}]}}}}"#, self.rules, self.normal, self.silent, self.atomic, self.compound_atomic, self.non_atomic, self.tags, self.push, self.pop, self.peek, self.drop, rule_count(&self.max_depth, "depth"), rule_count(&self.longest_rule, "lines"), self.alternatives, self.rules - self.missing_docs.len(), missing_docs.join(","))
+    }
+}
+
+/// The statistics as text, one item per line.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rules: {} (none {}, _ {}, @ {}, $ {}, ! {})", self.rules, self.normal, self.silent, self.atomic, self.compound_atomic, self.non_atomic)?;
+        writeln!(f, "tags: {}", self.tags)?;
+        writeln!(f, "stack: PUSH {}, POP {}, PEEK {}, DROP {}", self.push, self.pop, self.peek, self.drop)?;
+        if let Some((rule, depth)) = &self.max_depth {
+            writeln!(f, "max depth: {} ({})", depth, rule)?;
+        }
+        if let Some((rule, lines)) = &self.longest_rule {
+            writeln!(f, "longest rule: {} ({} lines)", rule, lines)?;
+        }
+        writeln!(f, "alternatives: {}", self.alternatives)?;
+        write!(f, "docs: {}/{} rules", self.rules - self.missing_docs.len(), self.rules)?;
+        if !self.missing_docs.is_empty() {
+            write!(f, ", missing: {}", self.missing_docs.join(", "))?;
+        }
+        writeln!(f)
+    }
+}
+
+/// The levels of nested expressions.
+fn depth(expr: &Expr) -> usize {
+    1 + expr.children().into_iter().map(depth).max().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const GRAMMAR: &str = indoc::indoc! {r#"
+        /// A file
+        file = { SOI ~ (item ~ ("," ~ item)*)? ~ EOI }
+
+        /// An item
+        item = {
+            #key = key ~ "=" ~ #value = value
+          | key
+        }
+        key   = @{ PUSH(ASCII_ALPHA+) ~ PEEK[..] ~ POP }
+        value = ${ "a" | "b" | "c" }
+        _ws   = _{ DROP | PEEK_ALL }
+        other = !{ "x" }
+    "#};
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::new(GRAMMAR).unwrap();
+        let expected = Stats { rules: 6, normal: 2, silent: 1, atomic: 1, compound_atomic: 1, non_atomic: 1, tags: 2, push: 1, pop: 1, peek: 2, drop: 1, max_depth: Some(("file".to_string(), 6)), longest_rule: Some(("item".to_string(), 4)), alternatives: 7, missing_docs: vec!["key".to_string(), "value".to_string(), "_ws".to_string(), "other".to_string()] };
+        assert_eq!(expected, stats);
+
+        let expected = indoc::indoc! {"
+            rules: 6 (none 2, _ 1, @ 1, $ 1, ! 1)
+            tags: 2
+            stack: PUSH 1, POP 1, PEEK 2, DROP 1
+            max depth: 6 (file)
+            longest rule: item (4 lines)
+            alternatives: 7
+            docs: 2/6 rules, missing: key, value, _ws, other
+        "};
+        assert_eq!(expected, stats.to_string());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let stats = Stats::new("/// A\na = { PUSH(b) }\nb = _{ \"b\" }").unwrap();
+        assert_eq!(r#"{"rules":{"total":2,"normal":1,"silent":1,"atomic":0,"compound_atomic":0,"non_atomic":0},"tags":0,"stack":{"push":1,"pop":0,"peek":0,"drop":0},"max_depth":{"rule":"a","depth":2},"longest_rule":{"rule":"a","lines":1},"alternatives":0,"docs":{"documented":1,"missing":["b"]}}"#, stats.to_json());
+
+        let stats = Stats::new("").unwrap();
+        assert!(stats.to_json().contains(r#""max_depth":null,"longest_rule":null"#));
+        assert_eq!("rules: 0 (none 0, _ 0, @ 0, $ 0, ! 0)\ntags: 0\nstack: PUSH 0, POP 0, PEEK 0, DROP 0\nalternatives: 0\ndocs: 0/0 rules\n", stats.to_string());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{build_walker, cache::Cache, format_file};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use pest_fmt::Config;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How long a file must stay unchanged before it is formatted, editors often save in several writes.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The modified time and length of a file.
+type Stamp = (SystemTime, u64);
+
+/// Watch the paths and format the `.pest` files when they change, never returns unless the watcher fails.
+pub(crate) fn watch(paths: Vec<String>, config: &Config, cache: Option<&Cache>, backup: bool) -> Result<(), Box<dyn Error>> {
+    let mut watcher = Watcher::new(paths.iter().map(PathBuf::from).collect(), DEBOUNCE)?;
+    println!("Watching for changes, press Ctrl-C to stop");
+
+    loop {
+        for path in watcher.wait()? {
+            match format_file(&path, &path, config, cache, backup) {
+                Ok((input, output)) if input != output => println!("Formatted {}", path.display()),
+                Ok(_) => (),
+                Err(e) => eprintln!("{}: {}", path.display(), e),
+            }
+            // Our own write must not trigger another format.
+            watcher.refresh(&path);
+        }
+    }
+}
+
+/// Detect the changed files by the notifications of the file system.
+///
+/// Directories are walked with `build_walker`, so the same excludes apply. They are only walked again when
+/// a `.pest` file that is not known yet changes, e.g.: a new file.
+pub(crate) struct Watcher {
+    paths: Vec<PathBuf>,
+    debounce: Duration,
+    /// The canonical paths of the files to format, to the paths as walked
+    files: HashMap<PathBuf, PathBuf>,
+    /// The stamps of the files after our own writes
+    written: HashMap<PathBuf, Stamp>,
+    /// The changed files and the time of their last change
+    pending: HashMap<PathBuf, Instant>,
+    events: Receiver<notify::Result<notify::Event>>,
+    // Dropping the watcher stops the notifications.
+    _watcher: RecommendedWatcher,
+}
+
+impl Watcher {
+    pub(crate) fn new(paths: Vec<PathBuf>, debounce: Duration) -> notify::Result<Watcher> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        for path in &paths {
+            if path.is_dir() {
+                watcher.watch(path, RecursiveMode::Recursive)?;
+            } else {
+                // Editors often replace the file on save, so its directory is watched.
+                let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                watcher.watch(parent, RecursiveMode::NonRecursive)?;
+            }
+        }
+
+        let files = scan(&paths);
+        Ok(Watcher { paths, debounce, files, written: HashMap::new(), pending: HashMap::new(), events, _watcher: watcher })
+    }
+
+    /// Wait for the changed files that have not changed again for the debounce duration, sorted by path.
+    pub(crate) fn wait(&mut self) -> notify::Result<Vec<PathBuf>> {
+        let stopped = || notify::Error::generic("the watcher stopped");
+        loop {
+            let event = match self.pending.values().min() {
+                Some(changed) => match self.events.recv_timeout((*changed + self.debounce).saturating_duration_since(Instant::now())) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => return Err(stopped()),
+                },
+                None => Some(self.events.recv().map_err(|_| stopped())?),
+            };
+
+            let mut paths = vec![];
+            for event in event.into_iter().chain(self.events.try_iter()) {
+                paths.extend(event?.paths);
+            }
+            self.changed(&paths, Instant::now());
+
+            let ready = self.ready(Instant::now());
+            if !ready.is_empty() {
+                return Ok(ready);
+            }
+        }
+    }
+
+    /// Record the paths of the notifications as changed at `now`, the paths that are not files to format
+    /// are ignored.
+    fn changed(&mut self, paths: &[PathBuf], now: Instant) {
+        let mut walked = false;
+        for path in paths {
+            let canonical = match path.canonicalize() {
+                Ok(canonical) => canonical,
+                // Removed
+                Err(_) => continue,
+            };
+            if !walked && !self.files.contains_key(&canonical) && canonical.is_file() && path.to_string_lossy().ends_with(".pest") {
+                self.files = scan(&self.paths);
+                walked = true;
+            }
+            if let Some(path) = self.files.get(&canonical) {
+                self.pending.insert(path.clone(), now);
+            }
+        }
+    }
+
+    /// The changed files that have not changed again for the debounce duration, sorted by path.
+    fn ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready: Vec<PathBuf> = self.pending.iter().filter(|(_, changed)| now.duration_since(**changed) >= self.debounce).map(|(path, _)| path.clone()).collect();
+        ready.sort();
+        for path in &ready {
+            self.pending.remove(path);
+        }
+
+        ready.retain(|path| path.is_file() && self.written.get(path).copied() != stamp(path));
+        ready
+    }
+
+    /// Record the current stamp of the file, so a write of our own is not reported as a change.
+    pub(crate) fn refresh(&mut self, path: &Path) {
+        if let Some(stamp) = stamp(path) {
+            self.written.insert(path.to_path_buf(), stamp);
+        }
+    }
+}
+
+/// The files to format in the paths, by their canonical paths.
+fn scan(paths: &[PathBuf]) -> HashMap<PathBuf, PathBuf> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_file() {
+            files.push(path.clone());
+        } else if path.is_dir() {
+            files.extend(build_walker(path, None).build().filter_map(Result::ok).map(|entry| entry.into_path()).filter(|path| path.is_file()));
+        }
+    }
+
+    files.into_iter().filter_map(|path| Some((path.canonicalize().ok()?, path))).collect()
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher() {
+        let root = std::env::temp_dir().join(format!("pestfmt-test-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.pest"), "a = { \"a\" }\n").unwrap();
+
+        let mut watcher = Watcher::new(vec![root.clone()], Duration::from_millis(50)).unwrap();
+
+        // The notifications of the file system
+        fs::write(root.join("a.pest"), "a={\"a\"}\n").unwrap();
+        let event = watcher.events.recv_timeout(Duration::from_secs(10)).unwrap().unwrap();
+        assert!(event.paths.iter().any(|path| path.ends_with("a.pest")));
+
+        fs::write(root.join("b.pest"), "b={\"b\"}\n").unwrap();
+        fs::write(root.join("c.txt"), "c={\"c\"}\n").unwrap();
+        let paths = vec![root.join("a.pest"), root.join("b.pest"), root.join("c.txt"), root.join("d.pest")];
+
+        // Debounced
+        let now = Instant::now();
+        watcher.changed(&paths, now);
+        assert!(watcher.ready(now).is_empty());
+        assert_eq!(vec![root.join("a.pest"), root.join("b.pest")], watcher.ready(now + Duration::from_millis(50)));
+        assert!(watcher.ready(now + Duration::from_millis(100)).is_empty());
+
+        // A write of our own
+        format_file(root.join("b.pest"), root.join("b.pest"), &Config::default(), None, false).unwrap();
+        watcher.refresh(&root.join("b.pest"));
+        watcher.changed(&paths[1..2], now);
+        assert!(watcher.ready(now + Duration::from_millis(200)).is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -25,4 +25,5 @@ fn test_files() {
     assert_format!("fixtures/graphql.actual.pest", "fixtures/graphql.expected.pest");
     assert_format!("fixtures/move.actual.pest", "fixtures/move.expected.pest");
     assert_format!("fixtures/vector.actual.pest", "fixtures/vector.expected.pest");
+    assert_format!("fixtures/crlf.actual.pest", "fixtures/crlf.expected.pest");
 }